@@ -0,0 +1,80 @@
+//! Compares looking up last edit dates with one `git log` per file (the approach used before the history was
+//! loaded in a single pass) against a full check mode run that uses the single pass
+
+use std::{fs, path::PathBuf, process::Command};
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use git2::{Repository, Signature};
+use zola_chrono::{run, Cli};
+
+const PAGE_COUNT: usize = 200;
+
+/// Creates a repository with `PAGE_COUNT` pages spread over a few commits
+fn create_repo() -> PathBuf {
+    let path = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("bench_last_edit_date");
+    if path.exists() {
+        fs::remove_dir_all(&path).expect("Failed to remove old bench repo");
+    }
+    fs::create_dir_all(path.join("content")).expect("Failed to create bench repo");
+    let repo = Repository::init(&path).expect("Failed to init repo");
+    let sig = Signature::now("bench_user", "bench_email").unwrap();
+    let mut parents = vec![];
+    for commit_num in 0..4 {
+        for page_num in (commit_num..PAGE_COUNT).step_by(4) {
+            fs::write(
+                path.join(format!("content/page_{page_num}.md")),
+                format!("+++\ntitle = \"Page {page_num}\"\ndate = 2020-01-01\n+++\n\nCommit {commit_num}\n"),
+            )
+            .unwrap();
+        }
+        let mut index = repo.index().unwrap();
+        index
+            .add_all(["*"], git2::IndexAddOption::DEFAULT, None)
+            .unwrap();
+        index.write().unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let parent_refs: Vec<_> = parents.iter().collect();
+        let oid = repo
+            .commit(Some("HEAD"), &sig, &sig, "bench", &tree, &parent_refs)
+            .unwrap();
+        parents = vec![repo.find_commit(oid).unwrap()];
+    }
+    path.canonicalize().unwrap()
+}
+
+fn last_edit_date(c: &mut Criterion) {
+    let repo_path = create_repo();
+    let content = repo_path.join("content");
+    let pages: Vec<_> = fs::read_dir(&content)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .collect();
+
+    let mut group = c.benchmark_group("last_edit_date");
+    group.sample_size(10);
+    group.bench_function("per_file_git_log", |b| {
+        b.iter(|| {
+            for page in pages.iter() {
+                let output = Command::new("git")
+                    .args(["log", "-1", "--format=%cs", page.to_string_lossy().as_ref()])
+                    .current_dir(&content)
+                    .output()
+                    .unwrap();
+                assert!(output.status.success());
+            }
+        })
+    });
+    group.bench_function("single_pass_check_run", |b| {
+        let cli = Cli {
+            root_path: content.to_string_lossy().to_string(),
+            unattended: true,
+            should_check_only: true,
+            ..Default::default()
+        };
+        b.iter(|| run(&cli).unwrap())
+    });
+    group.finish();
+}
+
+criterion_group!(benches, last_edit_date);
+criterion_main!(benches);
@@ -1,5 +1,7 @@
 //! Stores Command Line Interface (cli)  configuration
-use clap::Parser;
+use std::{cmp::Ordering, fmt::Display, str::FromStr};
+
+use clap::{Parser, Subcommand, ValueEnum};
 
 #[derive(Parser, Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Default)]
 #[command(
@@ -9,14 +11,29 @@ use clap::Parser;
     long_about = "Updates the `date` and `updated` fields of the pages front matter
     1. `date` should be the original publish date (Must exist and be today or earlier).
     2. `updated` should only be set if `date` is not equal to the last commit date, if it needs to be set it should match the last commit date
-"
+
+The last commit date follows renames. Commits that only move a file (without changing its content) are not counted as edits unless `--count-moves` is set.
+",
+    after_long_help = "Exit codes:
+  0  Success (In check mode no files would have been changed)
+  1  Any other error (Including invalid arguments)
+  2  Files would have been changed (Only in check mode)
+  3  No version control found or it has changes that could not be reverted
+  4  Some pages could not be processed (For example missing front matter or invalid TOML)
+  5  git could not be found
+  6  Aborted at the confirmation prompt"
 )]
 /// Stores the configurations acquired via the command line
 pub struct Cli {
+    #[command(subcommand)]
+    /// Runs a different command instead of updating the pages
+    pub command: Option<Command>,
+
     #[arg(value_name = "PATH", default_value = ".")]
     /// The root folder to start at
     ///
     /// Usually you want to point this to the content folder of the zola repo. It is required for it to be in a repository with a clean working tree.
+    /// If it is the root of a zola site (the folder with `config.toml`) then only the content folder is processed.
     pub root_path: String,
 
     /// If set will not prompt for confirmation before running
@@ -25,19 +42,341 @@ pub struct Cli {
 
     /// If set will not modify any files and only report how many files would have been changed
     ///
-    /// Return codes in this mode: (0) No files would have been changed (1) Error Occurred (2) Files would have been changed
+    /// Exits with code 2 if any files would have been changed (See the exit codes at the end of the help)
     #[arg(long = "check", short = 'c')]
     pub should_check_only: bool,
 
+    /// Only process the Markdown files staged for the next commit and stage the changes made to them (For use in a pre-commit hook)
+    ///
+    /// The staged changes are treated as an edit made today. Sections and bundles whose history includes a staged file (See `--sections children` and `--bundle-assets`) are processed too.
+    /// Pages that also have changes that are not staged are reported as errors instead of being changed.
+    /// Dirty files are allowed in this mode as only pages without unstaged changes are changed.
+    #[arg(long)]
+    pub staged: bool,
+
     /// Allows changes to be made even if there are dirty files in the vcs. WARNING: This means that there will be no easy way to undo changes made
     ///
     /// Prefer at least staging files if possible over using this option. Only provided in case users really prefer not needing to stage their files.
     #[arg(long)]
     pub allow_dirty: bool,
+
+    /// Commits that only change this part of a page are not counted as edits when finding the last edit date
+    ///
+    /// Useful to stop commits of changes made by this tool from being treated as edits to the content.
+    /// Defaults to `dates` with `--precision local-datetime` or `offset-datetime` as those commits are always after the values they set.
+    #[arg(long, value_enum, value_name = "PART")]
+    pub skip_commits_changing_only: Option<FrontMatterPart>,
+
+    /// Commits with a message that matches this regex are not counted as edits when finding the last edit date (Can be repeated)
+    ///
+    /// For example typo fixes or mass reformatting. Commits with the trailer `Chrono-Skip: true` are always skipped.
+    /// The commit that added a file is never skipped.
+    #[arg(long, value_name = "REGEX")]
+    pub skip_commit_pattern: Vec<String>,
+
+    /// Commits that only move or rename a page (without changing its content) are counted as edits when finding the last edit date
+    ///
+    /// The history of a page is followed across renames either way, this only decides if the move itself is an edit.
+    /// By default moving a page does not change its `updated`.
+    #[arg(long, overrides_with = "no_count_moves")]
+    pub count_moves: bool,
+
+    /// Turns off `--count-moves` (Overrides the configuration file)
+    #[arg(long, overrides_with = "count_moves")]
+    pub no_count_moves: bool,
+
+    /// Which date of a commit to use as the date of an edit [default: committer]
+    ///
+    /// The committer date changes when commits are rebased or cherry-picked while the author date does not.
+    #[arg(long, value_enum, value_name = "SOURCE")]
+    pub date_source: Option<DateSource>,
+
+    /// If `date` is missing it is set to the date of the commit that first added the page (following renames) instead of the last commit date
+    ///
+    /// Useful when adding dates to an existing site so that pages get their real publish dates.
+    /// Ignored with a warning in shallow clones as the commits that added the pages may not have been fetched.
+    #[arg(long, overrides_with = "no_date_from_first_commit")]
+    pub date_from_first_commit: bool,
+
+    /// Turns off `--date-from-first-commit` (Overrides the configuration file)
+    #[arg(long, overrides_with = "date_from_first_commit")]
+    pub no_date_from_first_commit: bool,
+
+    /// The date to use as today when applying the rules [default: the current date]
+    ///
+    /// Useful to get reproducible results, for example when replaying a previous run.
+    #[arg(long, value_name = "YYYY-MM-DD")]
+    pub today: Option<chrono::NaiveDate>,
+
+    /// The time zone used to find today's date and the dates of commits. Either an IANA name (eg. `Australia/Sydney`, `UTC`) or a fixed offset (eg. `+10:00`)
+    ///
+    /// If not set today is based on the local time zone of the machine and each commit uses the offset it was recorded with.
+    #[arg(long, value_name = "TZ")]
+    pub timezone: Option<TimeZone>,
+
+    /// How much of a timestamp is written when the tool sets `date` or `updated` [default: date]
+    ///
+    /// Values set from the git history use the timestamp of the commit and values set to today use the current time (or midnight if `--today` is set).
+    /// With a time `updated` is set to the timestamp of the last commit instead of today so that it matches it exactly.
+    /// With `local-datetime` or `offset-datetime` values that both have a time are compared as instants (Values without an offset are taken to be in `--timezone`) and other values are compared by day.
+    /// Comparisons with today are always by day. Existing values are always kept as written.
+    #[arg(long, value_enum, value_name = "PRECISION")]
+    pub precision: Option<Precision>,
+
+    /// Only process files that match this glob (Can be repeated)
+    ///
+    /// Patterns are matched against the path relative to PATH and relative to the root of the zola site (if found).
+    /// `*` does not match `/` but `**` does (Example: `blog/**`).
+    #[arg(long, value_name = "GLOB")]
+    pub include: Vec<String>,
+
+    /// Skip files that match this glob (Can be repeated)
+    ///
+    /// Patterns are matched the same way as for `--include` (Example: `**/drafts/**`). Exclude takes precedence over include.
+    #[arg(long, value_name = "GLOB")]
+    pub exclude: Vec<String>,
+
+    /// Only process files tracked by git (By default untracked files are processed unless they are ignored)
+    ///
+    /// Files ignored by git (`.gitignore`, `.git/info/exclude` and global excludes) are never processed.
+    #[arg(long, overrides_with = "no_tracked_only")]
+    pub tracked_only: bool,
+
+    /// Turns off `--tracked-only` (Overrides the configuration file)
+    #[arg(long, overrides_with = "tracked_only")]
+    pub no_tracked_only: bool,
+
+    /// How section files (`_index.md`) are processed [default: skip]
+    ///
+    /// Section files use the same rules as pages, only the last edit date they are compared against differs by mode.
+    #[arg(long, value_enum, value_name = "MODE")]
+    pub sections: Option<SectionMode>,
+
+    /// How draft pages (`draft = true`) are handled [default: process]
+    ///
+    /// With `defer` the `date` of a page is only set once it is published, instead of the first day it was worked on.
+    #[arg(long, value_enum, value_name = "POLICY")]
+    pub drafts: Option<DraftPolicy>,
+
+    /// How a date at the start of a file name (eg. `2023-04-01-my-post.md` or `2023-04-01_my-post/index.md`) is used [default: fix]
+    ///
+    /// Zola takes the date of a page from its name so by default it is the page's `date`. Use `report` to only warn when `date` is set to a different day.
+    #[arg(long, value_enum, value_name = "MODE")]
+    pub filename_date: Option<FilenameDateMode>,
+
+    /// For page bundles (`index.md`) the last edit is the newest commit that changed any file in the bundle's folder
+    ///
+    /// Useful when replacing an image or data file is a real update to the page. Only files that still exist are considered.
+    #[arg(long, overrides_with = "no_bundle_assets")]
+    pub bundle_assets: bool,
+
+    /// Turns off `--bundle-assets` (Overrides the configuration file)
+    #[arg(long, overrides_with = "bundle_assets")]
+    pub no_bundle_assets: bool,
+
+    /// Print a unified diff of the changes made to each file (or that would be made in check mode)
+    ///
+    /// With `--format json` the diffs are included in the report instead.
+    #[arg(long)]
+    pub diff: bool,
+
+    /// When to colour the diffs printed by `--diff`
+    #[arg(long, value_enum, value_name = "WHEN", default_value_t)]
+    pub color: ColorChoice,
+
+    /// How the results of the run are printed
+    ///
+    /// With `json` a report listing each file with its status and its `date` and `updated` before and after is printed to stdout while logs and the confirmation prompt go to stderr.
+    #[arg(long, value_enum, value_name = "FORMAT", default_value_t)]
+    pub format: OutputFormat,
+
+    /// Print the effective settings and where each one came from then exit without processing any files
+    ///
+    /// Settings are read from `zola_chrono.toml` or the `[extra.zola_chrono]` table in zola's `config.toml` (Searched for starting at PATH and moving up).
+    /// Settings passed on the command line take precedence over those in the file.
+    #[arg(long)]
+    pub show_config: bool,
+}
+
+/// Commands other than updating the pages
+#[derive(Subcommand, Clone, Eq, PartialEq, Ord, PartialOrd, Debug)]
+pub enum Command {
+    /// Show how the rules apply to a page and why, without changing it
+    ///
+    /// Prints the inputs found (last commit date and sha, original `date` and `updated`), any invalid values that were fixed up, the rule that decided the result and the resulting values.
+    Explain {
+        /// The page to explain
+        #[arg(value_name = "PAGE")]
+        page: String,
+    },
+}
+
+/// Formats the results of a run can be printed in
+#[derive(ValueEnum, Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Debug, Default)]
+pub enum OutputFormat {
+    /// A summary line for people
+    #[default]
+    Text,
+    /// A JSON report for other programs
+    Json,
+}
+
+/// When to use colour in the output
+#[derive(ValueEnum, Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Debug, Default)]
+pub enum ColorChoice {
+    /// Only when printing to a terminal and `NO_COLOR` is not set
+    #[default]
+    Auto,
+    /// Always
+    Always,
+    /// Never
+    Never,
+}
+
+/// Parts of the front matter used to decide if a commit only changed the front matter of a page
+#[derive(ValueEnum, Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Debug)]
+pub enum FrontMatterPart {
+    /// Only the `date` and `updated` lines
+    Dates,
+    /// Any line in the front matter
+    FrontMatter,
+}
+
+/// The date of a commit that is used as the date of the edit
+#[derive(ValueEnum, Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Debug, Default)]
+pub enum DateSource {
+    /// The date the commit was last applied (Changes on rebase, amend and cherry-pick)
+    #[default]
+    Committer,
+    /// The date the change was originally made
+    Author,
+}
+
+/// The precision of dates written to the front matter
+#[derive(ValueEnum, Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Debug, Default)]
+pub enum Precision {
+    /// Only the date (eg. `2023-05-01`)
+    #[default]
+    Date,
+    /// Date and time without an offset (eg. `2023-05-01T10:00:00`)
+    LocalDatetime,
+    /// Date and time with the offset from UTC (eg. `2023-05-01T10:00:00+02:00`)
+    OffsetDatetime,
+}
+
+/// How section files (`_index.md`) are processed
+#[derive(ValueEnum, Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Debug, Default)]
+pub enum SectionMode {
+    /// Section files are not processed
+    #[default]
+    Skip,
+    /// The last edit is taken from the history of the section file itself
+    Own,
+    /// The last edit is the newest last edit of the section's own pages, those directly in its folder or bundles in it (Falls back to the section file's own history if none of them have one)
+    Children,
+}
+
+/// How draft pages (`draft = true`) are handled
+#[derive(ValueEnum, Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Debug, Default)]
+pub enum DraftPolicy {
+    /// Drafts follow the same rules as other pages
+    #[default]
+    Process,
+    /// Drafts are left untouched
+    Skip,
+    /// `date` and `updated` are removed from drafts and a page without `date` gets today's date (or the date in its file name) once it is no longer a draft (Takes precedence over `--date-from-first-commit`)
+    ///
+    /// A page is only treated as just published if it was a draft in the last commit or was never committed, otherwise the usual rules apply.
+    Defer,
+}
+
+/// How a date at the start of a file name is used
+#[derive(ValueEnum, Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Debug, Default)]
+pub enum FilenameDateMode {
+    /// Dates in file names are not used
+    Ignore,
+    /// Used when `date` is not set and a warning is logged when `date` is set to a different day
+    Report,
+    /// Used when `date` is not set and replaces `date` when it is set to a different day
+    #[default]
+    Fix,
+}
+
+/// A time zone given either as an IANA name or as a fixed offset from UTC
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+pub enum TimeZone {
+    /// A named time zone from the IANA database (Accounts for daylight saving)
+    Iana(chrono_tz::Tz),
+    /// A fixed offset from UTC
+    Fixed(chrono::FixedOffset),
+}
+
+impl TimeZone {
+    /// Returns the date in this time zone at the instant `dt`
+    pub fn date_of<Tz: chrono::TimeZone>(&self, dt: &chrono::DateTime<Tz>) -> chrono::NaiveDate {
+        self.convert(dt).date_naive()
+    }
+
+    /// Returns the instant `dt` in this time zone (using the offset in effect at that instant)
+    pub fn convert<Tz: chrono::TimeZone>(
+        &self,
+        dt: &chrono::DateTime<Tz>,
+    ) -> chrono::DateTime<chrono::FixedOffset> {
+        match self {
+            TimeZone::Iana(tz) => dt.with_timezone(tz).fixed_offset(),
+            TimeZone::Fixed(offset) => dt.with_timezone(offset),
+        }
+    }
+}
+
+impl FromStr for TimeZone {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Ok(tz) = s.parse::<chrono_tz::Tz>() {
+            Ok(Self::Iana(tz))
+        } else if let Ok(offset) = s.parse::<chrono::FixedOffset>() {
+            Ok(Self::Fixed(offset))
+        } else {
+            Err(format!(
+                "{s:?} is not an IANA time zone name (eg. `Europe/London`) or a fixed offset (eg. `+10:00`)"
+            ))
+        }
+    }
+}
+
+impl Display for TimeZone {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TimeZone::Iana(tz) => write!(f, "{}", tz.name()),
+            TimeZone::Fixed(offset) => write!(f, "{offset}"),
+        }
+    }
+}
+
+impl Ord for TimeZone {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (TimeZone::Iana(a), TimeZone::Iana(b)) => a.name().cmp(b.name()),
+            (TimeZone::Fixed(a), TimeZone::Fixed(b)) => {
+                a.local_minus_utc().cmp(&b.local_minus_utc())
+            }
+            (TimeZone::Iana(_), TimeZone::Fixed(_)) => Ordering::Less,
+            (TimeZone::Fixed(_), TimeZone::Iana(_)) => Ordering::Greater,
+        }
+    }
+}
+
+impl PartialOrd for TimeZone {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
 }
 
 #[cfg(test)]
 mod tests {
+    use super::*;
+    use rstest::rstest;
 
     #[test]
     fn verify_cli() {
@@ -46,4 +385,21 @@ mod tests {
         use clap::CommandFactory;
         super::Cli::command().debug_assert()
     }
+
+    #[rstest]
+    #[case("UTC", "2024-01-01")]
+    #[case("Australia/Sydney", "2024-01-02")]
+    #[case("America/New_York", "2024-01-01")]
+    #[case("+10:00", "2024-01-02")]
+    #[case("-05:30", "2024-01-01")]
+    fn time_zone_date(#[case] time_zone: &str, #[case] expected: &str) {
+        let instant = chrono::DateTime::parse_from_rfc3339("2024-01-01T20:00:00Z").unwrap();
+        let time_zone: TimeZone = time_zone.parse().unwrap();
+        assert_eq!(time_zone.date_of(&instant).to_string(), expected);
+    }
+
+    #[test]
+    fn time_zone_invalid() {
+        assert!("Mars/Olympus_Mons".parse::<TimeZone>().is_err());
+    }
 }
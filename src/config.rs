@@ -0,0 +1,503 @@
+//! Loads settings from a configuration file and merges them with the settings from the command line
+//!
+//! The configuration is searched for starting at the root path and moving up through the parent folders.
+//! In each folder a `zola_chrono.toml` is checked first and then an `[extra.zola_chrono]` table in zola's `config.toml`.
+//! Settings passed on the command line take precedence over settings from the file.
+//! `allow_dirty` can only be set on the command line so that a file cannot turn off the safety check.
+
+use std::{
+    fmt::{Debug, Display},
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{bail, Context};
+use clap::ValueEnum;
+use tracing::debug;
+
+use crate::{cli::Cli, site::ZOLA_CONFIG_FILE_NAME};
+
+/// Name of the configuration file specific to this tool
+pub const CONFIG_FILE_NAME: &str = "zola_chrono.toml";
+
+/// Settings found in a configuration file (All optional so that unset values can be told apart)
+#[derive(Debug, Default, PartialEq)]
+struct FileSettings {
+    skip_commits_changing_only: Option<crate::FrontMatterPart>,
+    skip_commit_pattern: Option<Vec<String>>,
+    count_moves: Option<bool>,
+    date_source: Option<crate::DateSource>,
+    date_from_first_commit: Option<bool>,
+    timezone: Option<crate::TimeZone>,
+    precision: Option<crate::Precision>,
+    include: Option<Vec<String>>,
+    exclude: Option<Vec<String>>,
+    tracked_only: Option<bool>,
+    sections: Option<crate::SectionMode>,
+    drafts: Option<crate::DraftPolicy>,
+    filename_date: Option<crate::FilenameDateMode>,
+    bundle_assets: Option<bool>,
+}
+
+/// A configuration file that was found and the settings it contains
+#[derive(Debug)]
+pub struct ConfigFile {
+    path: PathBuf,
+    settings: FileSettings,
+}
+
+/// Where the value of a setting came from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SettingSource {
+    /// Passed on the command line
+    Cli,
+    /// Read from the configuration file at the path
+    ConfigFile(PathBuf),
+    /// Not set anywhere
+    Default,
+}
+
+impl Display for SettingSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SettingSource::Cli => write!(f, "command line"),
+            SettingSource::ConfigFile(path) => write!(f, "{path:?}"),
+            SettingSource::Default => write!(f, "default"),
+        }
+    }
+}
+
+/// The settings after merging the command line with the configuration file
+#[derive(Debug)]
+pub struct EffectiveConfig {
+    /// The merged settings
+    pub cli: Cli,
+    /// The name, value and source of each setting that can be set in the configuration file
+    pub sources: Vec<(&'static str, String, SettingSource)>,
+}
+
+impl Display for EffectiveConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (name, value, source) in &self.sources {
+            writeln!(f, "{name} = {value} ({source})")?;
+        }
+        Ok(())
+    }
+}
+
+impl ConfigFile {
+    /// Searches `start` and its ancestors for a configuration file
+    pub fn find(start: &Path) -> anyhow::Result<Option<Self>> {
+        for dir in start.ancestors() {
+            let path = dir.join(CONFIG_FILE_NAME);
+            if path.is_file() {
+                let text = fs::read_to_string(&path)
+                    .with_context(|| format!("Failed to read config file: {path:?}"))?;
+                let doc = text
+                    .parse::<toml_edit::DocumentMut>()
+                    .with_context(|| format!("Failed to parse config file: {path:?}"))?;
+                let settings = FileSettings::from_table(doc.as_table())
+                    .with_context(|| format!("Invalid settings in config file: {path:?}"))?;
+                return Ok(Some(Self { path, settings }));
+            }
+
+            let path = dir.join(ZOLA_CONFIG_FILE_NAME);
+            if path.is_file() {
+                // Other tools use the same name so a file that is not a zola config is not an error
+                let Some(doc) = fs::read_to_string(&path)
+                    .ok()
+                    .and_then(|text| text.parse::<toml_edit::DocumentMut>().ok())
+                else {
+                    debug!("Ignoring {path:?} as it could not be read as TOML");
+                    continue;
+                };
+                if let Some(table) = doc
+                    .get("extra")
+                    .and_then(|extra| extra.get("zola_chrono"))
+                    .and_then(|item| item.as_table_like())
+                {
+                    let settings = FileSettings::from_table(table).with_context(|| {
+                        format!("Invalid settings in [extra.zola_chrono] of {path:?}")
+                    })?;
+                    return Ok(Some(Self { path, settings }));
+                }
+            }
+        }
+        Ok(None)
+    }
+}
+
+impl FileSettings {
+    fn from_table(table: &dyn toml_edit::TableLike) -> anyhow::Result<Self> {
+        let mut result = Self::default();
+        for (key, item) in table.iter() {
+            match key {
+                "allow_dirty" => bail!("`{key}` can only be set on the command line"),
+                "skip_commits_changing_only" => {
+                    result.skip_commits_changing_only = Some(parse_value_enum(key, item)?)
+                }
+                "skip_commit_pattern" => {
+                    result.skip_commit_pattern = Some(parse_string_array(key, item)?)
+                }
+                "count_moves" => result.count_moves = Some(parse_bool(key, item)?),
+                "date_source" => result.date_source = Some(parse_value_enum(key, item)?),
+                "date_from_first_commit" => {
+                    result.date_from_first_commit = Some(parse_bool(key, item)?)
+                }
+                "timezone" => {
+                    result.timezone = Some(
+                        parse_str(key, item)?
+                            .parse()
+                            .map_err(|e| anyhow::anyhow!("Invalid value for `{key}`: {e}"))?,
+                    )
+                }
+                "precision" => result.precision = Some(parse_value_enum(key, item)?),
+                "include" => result.include = Some(parse_string_array(key, item)?),
+                "exclude" => result.exclude = Some(parse_string_array(key, item)?),
+                "tracked_only" => result.tracked_only = Some(parse_bool(key, item)?),
+                "sections" => result.sections = Some(parse_value_enum(key, item)?),
+                "drafts" => result.drafts = Some(parse_value_enum(key, item)?),
+                "filename_date" => result.filename_date = Some(parse_value_enum(key, item)?),
+                "bundle_assets" => result.bundle_assets = Some(parse_bool(key, item)?),
+                _ => bail!("Unknown setting: `{key}`"),
+            }
+        }
+        Ok(result)
+    }
+}
+
+fn parse_bool(key: &str, item: &toml_edit::Item) -> anyhow::Result<bool> {
+    item.as_bool()
+        .with_context(|| format!("Expected a boolean for `{key}`"))
+}
+
+fn parse_str<'a>(key: &str, item: &'a toml_edit::Item) -> anyhow::Result<&'a str> {
+    item.as_str()
+        .with_context(|| format!("Expected a string for `{key}`"))
+}
+
+fn parse_value_enum<T: ValueEnum>(key: &str, item: &toml_edit::Item) -> anyhow::Result<T> {
+    let value = parse_str(key, item)?;
+    T::from_str(value, false).map_err(|_| {
+        let possible: Vec<_> = T::value_variants()
+            .iter()
+            .filter_map(|v| v.to_possible_value())
+            .map(|v| v.get_name().to_string())
+            .collect();
+        anyhow::anyhow!(
+            "Invalid value {value:?} for `{key}`. Possible values: {}",
+            possible.join(", ")
+        )
+    })
+}
+
+fn parse_string_array(key: &str, item: &toml_edit::Item) -> anyhow::Result<Vec<String>> {
+    let array = item
+        .as_array()
+        .with_context(|| format!("Expected an array of strings for `{key}`"))?;
+    array
+        .iter()
+        .map(|value| {
+            value
+                .as_str()
+                .map(String::from)
+                .with_context(|| format!("Expected an array of strings for `{key}`"))
+        })
+        .collect()
+}
+
+/// Merges the settings from the command line with those from the config file (Command line takes precedence)
+pub fn merge(cli: &Cli, config: Option<ConfigFile>) -> EffectiveConfig {
+    let (path, settings) = match config {
+        Some(ConfigFile { path, settings }) => (Some(path), settings),
+        None => (None, Default::default()),
+    };
+    let mut sources = vec![];
+    let mut pick = |name: &'static str, from_cli: bool, in_file: bool, value: &dyn Debug| {
+        let source = match (from_cli, in_file, &path) {
+            (true, _, _) => SettingSource::Cli,
+            (false, true, Some(path)) => SettingSource::ConfigFile(path.clone()),
+            _ => SettingSource::Default,
+        };
+        sources.push((name, format!("{value:?}"), source));
+    };
+
+    let skip_commits_changing_only = cli
+        .skip_commits_changing_only
+        .or(settings.skip_commits_changing_only);
+    pick(
+        "skip_commits_changing_only",
+        cli.skip_commits_changing_only.is_some(),
+        settings.skip_commits_changing_only.is_some(),
+        &skip_commits_changing_only,
+    );
+
+    // Lists are not combined, if set on the command line the list from the file is ignored
+    let mut pick_list = |name, from_cli: &Vec<String>, in_file: Option<Vec<String>>| {
+        let is_in_file = in_file.is_some();
+        let value = if from_cli.is_empty() {
+            in_file.unwrap_or_default()
+        } else {
+            from_cli.clone()
+        };
+        pick(name, !from_cli.is_empty(), is_in_file, &value);
+        value
+    };
+    let skip_commit_pattern = pick_list(
+        "skip_commit_pattern",
+        &cli.skip_commit_pattern,
+        settings.skip_commit_pattern,
+    );
+    let include = pick_list("include", &cli.include, settings.include);
+    let exclude = pick_list("exclude", &cli.exclude, settings.exclude);
+
+    let count_moves = merge_flag(cli.count_moves, cli.no_count_moves, settings.count_moves);
+    pick(
+        "count_moves",
+        cli.count_moves || cli.no_count_moves,
+        settings.count_moves.is_some(),
+        &count_moves,
+    );
+
+    let tracked_only = merge_flag(cli.tracked_only, cli.no_tracked_only, settings.tracked_only);
+    pick(
+        "tracked_only",
+        cli.tracked_only || cli.no_tracked_only,
+        settings.tracked_only.is_some(),
+        &tracked_only,
+    );
+
+    let date_source = cli.date_source.or(settings.date_source);
+    pick(
+        "date_source",
+        cli.date_source.is_some(),
+        settings.date_source.is_some(),
+        &date_source.unwrap_or_default(),
+    );
+
+    let date_from_first_commit = merge_flag(
+        cli.date_from_first_commit,
+        cli.no_date_from_first_commit,
+        settings.date_from_first_commit,
+    );
+    pick(
+        "date_from_first_commit",
+        cli.date_from_first_commit || cli.no_date_from_first_commit,
+        settings.date_from_first_commit.is_some(),
+        &date_from_first_commit,
+    );
+
+    let timezone = cli.timezone.or(settings.timezone);
+    pick(
+        "timezone",
+        cli.timezone.is_some(),
+        settings.timezone.is_some(),
+        &timezone.map(|tz| tz.to_string()),
+    );
+
+    let precision = cli.precision.or(settings.precision);
+    pick(
+        "precision",
+        cli.precision.is_some(),
+        settings.precision.is_some(),
+        &precision.unwrap_or_default(),
+    );
+
+    let sections = cli.sections.or(settings.sections);
+    pick(
+        "sections",
+        cli.sections.is_some(),
+        settings.sections.is_some(),
+        &sections.unwrap_or_default(),
+    );
+
+    let drafts = cli.drafts.or(settings.drafts);
+    pick(
+        "drafts",
+        cli.drafts.is_some(),
+        settings.drafts.is_some(),
+        &drafts.unwrap_or_default(),
+    );
+
+    let filename_date = cli.filename_date.or(settings.filename_date);
+    pick(
+        "filename_date",
+        cli.filename_date.is_some(),
+        settings.filename_date.is_some(),
+        &filename_date.unwrap_or_default(),
+    );
+
+    let bundle_assets = merge_flag(
+        cli.bundle_assets,
+        cli.no_bundle_assets,
+        settings.bundle_assets,
+    );
+    pick(
+        "bundle_assets",
+        cli.bundle_assets || cli.no_bundle_assets,
+        settings.bundle_assets.is_some(),
+        &bundle_assets,
+    );
+
+    EffectiveConfig {
+        cli: Cli {
+            skip_commits_changing_only,
+            skip_commit_pattern,
+            count_moves,
+            date_source,
+            date_from_first_commit,
+            timezone,
+            precision,
+            include,
+            exclude,
+            tracked_only,
+            sections,
+            drafts,
+            filename_date,
+            bundle_assets,
+            ..cli.clone()
+        },
+        sources,
+    }
+}
+
+/// The value of a flag that can be turned on (`--flag`) or off (`--no-flag`) from the command line, falling back to the file if neither was passed
+fn merge_flag(on: bool, off: bool, in_file: Option<bool>) -> bool {
+    if on || off {
+        on
+    } else {
+        in_file.unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+
+    use super::*;
+    use crate::{
+        DateSource, DraftPolicy, FilenameDateMode, FrontMatterPart, Precision, SectionMode,
+    };
+
+    fn settings_from_str(s: &str) -> anyhow::Result<FileSettings> {
+        let doc = s.parse::<toml_edit::DocumentMut>().unwrap();
+        FileSettings::from_table(doc.as_table())
+    }
+
+    #[test]
+    fn parse_all_settings() {
+        let actual = settings_from_str(
+            r#"
+skip_commits_changing_only = "front-matter"
+skip_commit_pattern = ["typo", "^fmt"]
+count_moves = true
+date_source = "author"
+date_from_first_commit = true
+timezone = "+10:00"
+precision = "offset-datetime"
+include = ["blog/**"]
+exclude = ["**/drafts/**"]
+tracked_only = true
+sections = "children"
+drafts = "defer"
+filename_date = "fix"
+bundle_assets = true
+"#,
+        )
+        .unwrap();
+        let expected = FileSettings {
+            skip_commits_changing_only: Some(FrontMatterPart::FrontMatter),
+            skip_commit_pattern: Some(vec!["typo".to_string(), "^fmt".to_string()]),
+            count_moves: Some(true),
+            date_source: Some(DateSource::Author),
+            date_from_first_commit: Some(true),
+            timezone: Some("+10:00".parse().unwrap()),
+            precision: Some(Precision::OffsetDatetime),
+            include: Some(vec!["blog/**".to_string()]),
+            exclude: Some(vec!["**/drafts/**".to_string()]),
+            tracked_only: Some(true),
+            sections: Some(SectionMode::Children),
+            drafts: Some(DraftPolicy::Defer),
+            filename_date: Some(FilenameDateMode::Fix),
+            bundle_assets: Some(true),
+        };
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn reject_invalid_settings() {
+        assert!(settings_from_str("unknown = 1").is_err());
+        assert!(settings_from_str("date_source = \"publisher\"").is_err());
+        assert!(settings_from_str("allow_dirty = true").is_err());
+        assert!(settings_from_str("tracked_only = \"yes\"").is_err());
+        assert!(settings_from_str("timezone = \"Nowhere/Place\"").is_err());
+        assert!(settings_from_str("sections = \"all\"").is_err());
+    }
+
+    #[test]
+    fn cli_takes_precedence() {
+        let path = PathBuf::from("/site/zola_chrono.toml");
+        let config = ConfigFile {
+            path: path.clone(),
+            settings: settings_from_str(
+                "date_source = \"author\"\nprecision = \"local-datetime\"\nskip_commit_pattern = [\"typo\"]",
+            )
+            .unwrap(),
+        };
+        let cli = Cli {
+            precision: Some(Precision::Date),
+            ..Default::default()
+        };
+        let actual = merge(&cli, Some(config));
+        assert_eq!(actual.cli.date_source, Some(DateSource::Author));
+        assert_eq!(actual.cli.precision, Some(Precision::Date));
+        assert_eq!(actual.cli.skip_commit_pattern, vec!["typo".to_string()]);
+        let source_of = |name| {
+            actual
+                .sources
+                .iter()
+                .find(|(n, _, _)| *n == name)
+                .map(|(_, _, source)| source.clone())
+                .unwrap()
+        };
+        assert_eq!(source_of("date_source"), SettingSource::ConfigFile(path));
+        assert_eq!(source_of("precision"), SettingSource::Cli);
+        assert_eq!(source_of("timezone"), SettingSource::Default);
+    }
+
+    #[test]
+    fn cli_turns_off_flags() {
+        let path = PathBuf::from("/site/zola_chrono.toml");
+        let config = ConfigFile {
+            path: path.clone(),
+            settings: settings_from_str(
+                "tracked_only = true\nbundle_assets = true\ndate_from_first_commit = true",
+            )
+            .unwrap(),
+        };
+        let cli = Cli::parse_from([
+            "zola_chrono",
+            "--no-tracked-only",
+            "--bundle-assets",
+            "--no-bundle-assets",
+        ]);
+        let actual = merge(&cli, Some(config));
+        assert!(!actual.cli.tracked_only);
+        assert!(!actual.cli.bundle_assets);
+        assert!(actual.cli.date_from_first_commit);
+        let source_of = |name| {
+            actual
+                .sources
+                .iter()
+                .find(|(n, _, _)| *n == name)
+                .map(|(_, _, source)| source.clone())
+                .unwrap()
+        };
+        assert_eq!(source_of("tracked_only"), SettingSource::Cli);
+        assert_eq!(source_of("bundle_assets"), SettingSource::Cli);
+        assert_eq!(
+            source_of("date_from_first_commit"),
+            SettingSource::ConfigFile(path)
+        );
+    }
+}
@@ -0,0 +1,196 @@
+//! Errors returned by the library
+
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+
+/// The errors that can stop a run or the processing of a single file
+#[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
+pub enum Error {
+    /// Version control is missing or has changes that could not be reverted
+    #[error("Failed to find a clean version control system. Files must be at least staged before tool can run or you can opt-out of being able to revert changes. See help for more info.\nPath:{path:?}")]
+    UncleanVcs {
+        /// The folder (or file) being processed
+        path: PathBuf,
+        /// What the check found
+        #[source]
+        source: version_control_clean_check::VCSError,
+    },
+    /// The file does not start with TOML or YAML front matter
+    #[error("Failed to find front matter in {path:?}")]
+    MissingFrontMatter {
+        /// The file being processed
+        path: PathBuf,
+    },
+    /// The front matter of the file is not valid TOML
+    #[error("Failed to parse TOML in front matter of {path:?}")]
+    InvalidToml {
+        /// The file being processed
+        path: PathBuf,
+        /// The parse error
+        #[source]
+        source: toml_edit::TomlError,
+    },
+    /// The front matter of the file is not YAML that can be edited safely (or has an invalid `date` or `updated`)
+    #[error("Failed to parse YAML in front matter of {path:?}: {message}")]
+    InvalidYaml {
+        /// The file being processed
+        path: PathBuf,
+        /// What is wrong and where
+        message: String,
+    },
+    /// git could not be run or exited with an error
+    #[error("Running git failed in {path:?}: {message}")]
+    Git {
+        /// The folder git was run in
+        path: PathBuf,
+        /// What went wrong (Includes the arguments and output of git if it ran)
+        message: String,
+        /// Set if git could not be started (For example if it is not installed)
+        #[source]
+        source: Option<std::io::Error>,
+    },
+    /// The last edit found for the file is after the date used as today
+    #[error("Got a LAST edit date in the future...? We think today is: {today} and last edit date found is {last_edit} for path {path:?}")]
+    FutureCommitDate {
+        /// The file being processed
+        path: PathBuf,
+        /// The date used as today
+        today: String,
+        /// The date of the last edit found
+        last_edit: String,
+    },
+    /// Reading or writing a file or folder failed
+    #[error("Failed to access {path:?}")]
+    Io {
+        /// The file or folder that could not be accessed
+        path: PathBuf,
+        /// The underlying error
+        #[source]
+        source: std::io::Error,
+    },
+    /// The user did not confirm that changes should be made
+    #[error("Aborted at users request")]
+    Aborted {
+        /// The folder (or file) that would have been processed
+        path: PathBuf,
+    },
+    /// Any other failure (For example invalid settings)
+    #[error(transparent)]
+    Other(anyhow::Error),
+}
+
+/// Identifies the variant of an [`Error`] without its details (Used in reports)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum ErrorKind {
+    /// See [`Error::UncleanVcs`]
+    UncleanVcs,
+    /// See [`Error::MissingFrontMatter`]
+    MissingFrontMatter,
+    /// See [`Error::InvalidToml`]
+    InvalidToml,
+    /// See [`Error::InvalidYaml`]
+    InvalidYaml,
+    /// See [`Error::Git`]
+    Git,
+    /// See [`Error::FutureCommitDate`]
+    FutureCommitDate,
+    /// See [`Error::Io`]
+    Io,
+    /// See [`Error::Aborted`]
+    Aborted,
+    /// See [`Error::Other`]
+    Other,
+}
+
+impl Error {
+    /// The variant of this error
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Error::UncleanVcs { .. } => ErrorKind::UncleanVcs,
+            Error::MissingFrontMatter { .. } => ErrorKind::MissingFrontMatter,
+            Error::InvalidToml { .. } => ErrorKind::InvalidToml,
+            Error::InvalidYaml { .. } => ErrorKind::InvalidYaml,
+            Error::Git { .. } => ErrorKind::Git,
+            Error::FutureCommitDate { .. } => ErrorKind::FutureCommitDate,
+            Error::Io { .. } => ErrorKind::Io,
+            Error::Aborted { .. } => ErrorKind::Aborted,
+            Error::Other(_) => ErrorKind::Other,
+        }
+    }
+
+    /// The path the error relates to (None for [`Error::Other`])
+    pub fn path(&self) -> Option<&Path> {
+        match self {
+            Error::UncleanVcs { path, .. }
+            | Error::MissingFrontMatter { path }
+            | Error::InvalidToml { path, .. }
+            | Error::InvalidYaml { path, .. }
+            | Error::Git { path, .. }
+            | Error::FutureCommitDate { path, .. }
+            | Error::Io { path, .. }
+            | Error::Aborted { path } => Some(path),
+            Error::Other(_) => None,
+        }
+    }
+
+    /// The kind of the first [`Error`] in the chain of `error` ([`ErrorKind::Other`] if there is none)
+    pub(crate) fn kind_of(error: &anyhow::Error) -> ErrorKind {
+        error
+            .chain()
+            .find_map(|cause| cause.downcast_ref::<Error>())
+            .map_or(ErrorKind::Other, Error::kind)
+    }
+
+    /// Shorthand for [`Error::Io`] to use with `map_err`
+    pub(crate) fn io(path: &Path) -> impl FnOnce(std::io::Error) -> Self + '_ {
+        move |source| Error::Io {
+            path: path.to_path_buf(),
+            source,
+        }
+    }
+}
+
+impl From<anyhow::Error> for Error {
+    /// Recovers the typed error if there is one in the chain (Any context added on top is dropped)
+    fn from(error: anyhow::Error) -> Self {
+        match error.downcast::<Error>() {
+            Ok(error) => error,
+            Err(error) => Error::Other(error),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Context as _;
+
+    use super::*;
+
+    #[test]
+    fn typed_error_survives_context() {
+        let inner = Error::MissingFrontMatter {
+            path: "a.md".into(),
+        };
+        let wrapped = Err::<(), _>(inner)
+            .context("Processing failed")
+            .context("Outer")
+            .unwrap_err();
+        assert_eq!(Error::kind_of(&wrapped), ErrorKind::MissingFrontMatter);
+        let recovered = Error::from(wrapped);
+        assert_eq!(recovered.kind(), ErrorKind::MissingFrontMatter);
+        assert_eq!(recovered.path(), Some(Path::new("a.md")));
+    }
+
+    #[test]
+    fn untyped_error_is_other() {
+        let error = anyhow::anyhow!("Invalid setting");
+        assert_eq!(Error::kind_of(&error), ErrorKind::Other);
+        let error = Error::from(error);
+        assert_eq!(error.kind(), ErrorKind::Other);
+        assert_eq!(error.to_string(), "Invalid setting");
+    }
+}
@@ -0,0 +1,84 @@
+//! Describes how the rules were applied to a single page (Output of the `explain` command)
+
+use std::{fmt::Display, path::PathBuf};
+
+use crate::stats::SkipReason;
+
+/// How the rules for `date` and `updated` were applied to a page
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RuleTrace {
+    /// `date` as it was before the rules were applied
+    pub org_date: Option<String>,
+    /// `updated` as it was before the rules were applied
+    pub org_updated: Option<String>,
+    /// The last edit date the rules compared against
+    pub last_edit_date: Option<String>,
+    /// Used as `date` when `date` is missing (From the file name or the first commit)
+    pub fallback_date: Option<String>,
+    /// The date the rules treated as today
+    pub today: String,
+    /// Invalid values found and how they were treated (These are also logged as warnings)
+    pub fixups: Vec<String>,
+    /// The rule that decided the result
+    pub rule: &'static str,
+    /// `date` after the rules were applied
+    pub new_date: String,
+    /// `updated` after the rules were applied
+    pub new_updated: Option<String>,
+}
+
+/// Everything that went into the decision for a single page
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Explanation {
+    /// Path of the page
+    pub path: PathBuf,
+    /// Set if the page would not be processed
+    pub skip_reason: Option<SkipReason>,
+    /// The file whose history was used if not the page itself (A child page of a section or an asset of a bundle)
+    pub history_source: Option<PathBuf>,
+    /// Timestamp of the last commit counted as an edit
+    pub last_commit_date: Option<String>,
+    /// Hash of the last commit counted as an edit
+    pub last_commit: Option<String>,
+    /// Steps taken before the rules were applied (Draft handling and dates in file names)
+    pub notes: Vec<String>,
+    /// How the rules were applied (None if they were not applied)
+    pub rules: Option<RuleTrace>,
+}
+
+impl Display for Explanation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let or_none = |value: &Option<String>| value.clone().unwrap_or_else(|| "(none)".into());
+        writeln!(f, "Page: {:?}", self.path)?;
+        if let Some(reason) = self.skip_reason {
+            return writeln!(f, "Skipped: {reason}");
+        }
+        writeln!(f, "Inputs:")?;
+        if let Some(source) = &self.history_source {
+            writeln!(f, "  history from:  {source:?}")?;
+        }
+        writeln!(f, "  last commit:   {}", or_none(&self.last_commit_date))?;
+        writeln!(f, "  commit sha:    {}", or_none(&self.last_commit))?;
+        let Some(rules) = &self.rules else {
+            writeln!(f, "  date:          (not checked)")?;
+            for note in self.notes.iter() {
+                writeln!(f, "Note: {note}")?;
+            }
+            return Ok(());
+        };
+        writeln!(f, "  date:          {}", or_none(&rules.org_date))?;
+        writeln!(f, "  updated:       {}", or_none(&rules.org_updated))?;
+        writeln!(f, "  fallback date: {}", or_none(&rules.fallback_date))?;
+        writeln!(f, "  today:         {}", rules.today)?;
+        for note in self.notes.iter() {
+            writeln!(f, "Note: {note}")?;
+        }
+        for fixup in rules.fixups.iter() {
+            writeln!(f, "Fixup: {fixup}")?;
+        }
+        writeln!(f, "Rule: {}", rules.rule)?;
+        writeln!(f, "Result:")?;
+        writeln!(f, "  date:          {}", rules.new_date)?;
+        writeln!(f, "  updated:       {}", or_none(&rules.new_updated))
+    }
+}
@@ -7,68 +7,131 @@
 #![doc = include_str!("../README.md")]
 
 mod cli;
+mod config;
+mod error;
+mod explain;
 mod processing;
+mod site;
 mod stats;
 
-use crate::processing::walk_directory;
-use anyhow::Context;
+use crate::{
+    config::{merge, ConfigFile},
+    processing::{explain_file, walk_directory},
+    site::ZolaSite,
+};
+use anyhow::anyhow;
 use std::{
     env,
     io::{self, Write},
     path::{Path, PathBuf},
     time::Instant,
 };
-use tracing::info;
+use tracing::{debug, info};
 use version_control_clean_check::{check_version_control, CheckOptions};
 
-pub use cli::Cli;
-pub use stats::Stats;
+pub use cli::{
+    Cli, ColorChoice, Command, DateSource, DraftPolicy, FilenameDateMode, FrontMatterPart,
+    OutputFormat, Precision, SectionMode, TimeZone,
+};
+pub use error::{Error, ErrorKind};
+pub use explain::{Explanation, RuleTrace};
+pub use stats::{FileRecord, FileStatus, SkipReason, Stats};
 
 /// Runs the body of the logic
-pub fn run(cli: &Cli) -> anyhow::Result<Stats> {
+pub fn run(cli: &Cli) -> Result<Stats, Error> {
     // This also checks that the path exists as that is required for canonicalization
-    let root_path = PathBuf::from(&cli.root_path)
+    let mut root_path = PathBuf::from(&cli.root_path)
         .canonicalize()
-        .with_context(|| format!("Failed to canonicalize path: '{}'", cli.root_path))?;
+        .map_err(Error::io(Path::new(&cli.root_path)))?;
+
+    // Merge settings from the config file (if any) with those passed in
+    let config_search_start = if root_path.is_file() {
+        root_path.parent().unwrap_or(&root_path)
+    } else {
+        &root_path
+    };
+    let config = ConfigFile::find(config_search_start)?;
+    let effective = merge(cli, config);
+    debug!("Effective settings:\n{effective}");
+    if cli.show_config {
+        print!("{effective}");
+        return Ok(Default::default());
+    }
+    let cli = &effective.cli;
+
+    // When pointed at the root of a zola site only process the content (Not themes, templates, etc.)
+    let site = ZolaSite::find(config_search_start)?;
+    if let Some(site) = site.as_ref().filter(|site| site.root() == root_path) {
+        let content_dir = site.content_dir();
+        root_path = content_dir
+            .canonicalize()
+            .map_err(Error::io(&content_dir))?;
+        info!("Zola site found. Processing content folder: {root_path:?}");
+    }
 
     let check_options = CheckOptions {
         // This makes it possible for the user to undo our changes if any so this is fine
         allow_staged: true,
-        // Set when dirty is allowed (Either we aren't going to make changes so it's fine or the user opted into allowing dirty files)
-        allow_dirty: cli.should_check_only || cli.allow_dirty,
+        // Set when dirty is allowed (Either we aren't going to make changes so it's fine, we only change fully staged files or the user opted into allowing dirty files)
+        allow_dirty: cli.should_check_only || cli.staged || cli.allow_dirty,
         ..Default::default()
     };
 
     // Confirm it is safe to make changes
-    check_version_control(&root_path, &check_options).with_context(|| {
-        format!(
-            "Failed to find a clean version control system. Files must be at least staged before tool can run or you can opt-out of being able to revert changes. See help for more info.\nPath:{root_path:?}"
-        )
+    check_version_control(&root_path, &check_options).map_err(|source| Error::UncleanVcs {
+        path: root_path.clone(),
+        source,
     })?;
 
     // Confirm user wants to make changes
-    if !cli.should_check_only && !cli.unattended && !confirm_proceed(&root_path) {
-        println!("Aborted at users request");
-        return Ok(Default::default());
+    if !cli.should_check_only && !cli.unattended && !confirm_proceed(&root_path, cli.format) {
+        return Err(Error::Aborted { path: root_path });
     }
 
     // Change current working directory to target folder so that git commands will work correctly
-    env::set_current_dir(&root_path).context("Failed change working directory to {root_path:?}")?;
+    env::set_current_dir(&root_path).map_err(Error::io(&root_path))?;
 
     // Walk tree and process files
     let start = Instant::now();
-    let result = walk_directory(&root_path, cli)?;
+    let result = walk_directory(&root_path, cli, site)?;
     info!(
         "Run duration: {} ms",
         Instant::now().duration_since(start).as_millis()
     );
-    println!("Run Completed");
+    if cli.format == OutputFormat::Text {
+        // Other formats own stdout
+        println!("Run Completed");
+    }
     Ok(result)
 }
 
-fn confirm_proceed(root_path: &Path) -> bool {
-    print!("Are you sure you want to update dates at {root_path:?}? (enter 'yes' to proceed) ");
-    io::stdout().flush().expect("Failed to flush to stdout");
+/// Explains how the rules apply to the page at `page` without changing it (Settings are taken from `cli` and the config file)
+pub fn explain(cli: &Cli, page: &str) -> Result<Explanation, Error> {
+    let path = PathBuf::from(page)
+        .canonicalize()
+        .map_err(Error::io(Path::new(page)))?;
+    let Some(dir) = path.parent().filter(|_| path.is_file()) else {
+        return Err(anyhow!("Expected the path of a page but got: {path:?}").into());
+    };
+    let config = ConfigFile::find(dir)?;
+    let effective = merge(cli, config);
+    debug!("Effective settings:\n{effective}");
+    let site = ZolaSite::find(dir)?;
+    Ok(explain_file(&path, &effective.cli, site)?)
+}
+
+/// Asks the user to confirm on stdout (stderr with `--format json` so stdout only has the report)
+fn confirm_proceed(root_path: &Path, format: OutputFormat) -> bool {
+    let mut out: Box<dyn Write> = match format {
+        OutputFormat::Text => Box::new(io::stdout()),
+        OutputFormat::Json => Box::new(io::stderr()),
+    };
+    write!(
+        out,
+        "Are you sure you want to update dates at {root_path:?}? (enter 'yes' to proceed) "
+    )
+    .expect("Failed to write prompt");
+    out.flush().expect("Failed to flush prompt");
 
     let mut user_input = String::new();
     io::stdin()
@@ -1,31 +1,157 @@
-use anyhow::bail;
 use clap::Parser;
+use std::{io::IsTerminal as _, process::ExitCode};
 use tracing::{debug, error};
-use tracing_subscriber::{fmt, layer::SubscriberExt as _, util::SubscriberInitExt as _, EnvFilter};
-use zola_chrono::{self, run, Cli};
+use tracing_subscriber::{
+    fmt::{self, writer::BoxMakeWriter},
+    layer::SubscriberExt as _,
+    util::SubscriberInitExt as _,
+    EnvFilter,
+};
 
-fn main() -> anyhow::Result<()> {
-    let cli: Cli = Cli::parse();
-    init_tracing();
+use zola_chrono::{self, explain, run, Cli, ColorChoice, Command, Error, OutputFormat, Stats};
+
+/// The exit codes of the program (Documented in the README and the long help so they must stay stable)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Exit {
+    Success = 0,
+    Failure = 1,
+    WouldChange = 2,
+    UncleanVcs = 3,
+    PageErrors = 4,
+    GitNotFound = 5,
+    Aborted = 6,
+}
+
+impl Exit {
+    /// Finds the category of a failure that stopped the run
+    fn from_error(error: &anyhow::Error) -> Self {
+        match error.downcast_ref::<Error>() {
+            Some(Error::UncleanVcs { .. }) => Exit::UncleanVcs,
+            Some(Error::Git {
+                source: Some(source),
+                ..
+            }) if source.kind() == std::io::ErrorKind::NotFound => Exit::GitNotFound,
+            // Only returned by `explain` as a run records the errors of each page instead of stopping
+            Some(
+                Error::MissingFrontMatter { .. }
+                | Error::InvalidToml { .. }
+                | Error::InvalidYaml { .. }
+                | Error::FutureCommitDate { .. },
+            ) => Exit::PageErrors,
+            Some(Error::Aborted { .. }) => Exit::Aborted,
+            _ => Exit::Failure,
+        }
+    }
+}
+
+impl From<Exit> for ExitCode {
+    fn from(value: Exit) -> Self {
+        ExitCode::from(value as u8)
+    }
+}
+
+fn main() -> ExitCode {
+    let cli = match Cli::try_parse() {
+        Ok(cli) => cli,
+        Err(e) => {
+            // Not using `exit` from clap as it uses 2 for usage errors which is taken by "would change"
+            let _ = e.print();
+            return if e.use_stderr() {
+                Exit::Failure
+            } else {
+                Exit::Success // Help or version was requested
+            }
+            .into();
+        }
+    };
+    init_tracing(cli.format);
     debug!("Cli: {cli:#?}");
-    let stats = run(&cli)?;
-    println!("File Stats: {stats}");
+    match try_main(&cli) {
+        Ok(exit) => exit.into(),
+        Err(e) => {
+            let exit = Exit::from_error(&e);
+            if exit == Exit::Aborted && cli.format == OutputFormat::Text {
+                println!("{e}");
+            } else {
+                eprintln!("Error: {e:?}");
+            }
+            exit.into()
+        }
+    }
+}
+
+fn try_main(cli: &Cli) -> anyhow::Result<Exit> {
+    if let Some(Command::Explain { page }) = &cli.command {
+        print!("{}", explain(cli, page)?);
+        return Ok(Exit::Success);
+    }
+    let stats = run(cli)?;
+    if cli.show_config {
+        return Ok(Exit::Success);
+    }
+    match cli.format {
+        OutputFormat::Text => {
+            print_diffs(&stats, cli.color);
+            println!("File Stats: {stats}");
+        }
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&stats)?),
+    }
     if stats.errors() == 0 {
         if cli.should_check_only && stats.changed() > 0 {
-            println!("{} files would have been changed", stats.changed());
-            std::process::exit(2);
+            if cli.format == OutputFormat::Text {
+                println!("{} files would have been changed", stats.changed());
+            }
+            return Ok(Exit::WouldChange);
         }
-        Ok(())
+        Ok(Exit::Success)
     } else {
         let msg = format!("Run FAILED! {} errors", stats.errors());
         error!("{msg}");
-        bail!("{msg}");
+        eprintln!("Error: {msg}");
+        Ok(Exit::PageErrors)
+    }
+}
+
+fn print_diffs(stats: &Stats, color: ColorChoice) {
+    let use_color = match color {
+        ColorChoice::Auto => {
+            std::io::stdout().is_terminal() && std::env::var_os("NO_COLOR").is_none()
+        }
+        ColorChoice::Always => true,
+        ColorChoice::Never => false,
+    };
+    for diff in stats
+        .files()
+        .iter()
+        .filter_map(|record| record.diff.as_ref())
+    {
+        for line in diff.lines() {
+            let style = if !use_color {
+                anstyle::Style::new()
+            } else if line.starts_with("--- a/") || line.starts_with("+++ b/") {
+                anstyle::Style::new().bold()
+            } else if line.starts_with('-') {
+                anstyle::AnsiColor::Red.on_default()
+            } else if line.starts_with('+') {
+                anstyle::AnsiColor::Green.on_default()
+            } else if line.starts_with("@@") {
+                anstyle::AnsiColor::Cyan.on_default()
+            } else {
+                anstyle::Style::new()
+            };
+            println!("{style}{line}{style:#}");
+        }
     }
 }
 
-fn init_tracing() {
+fn init_tracing(format: OutputFormat) {
+    // Keep stdout for the report when it is meant to be read by other programs
+    let writer = match format {
+        OutputFormat::Text => BoxMakeWriter::new(std::io::stdout),
+        OutputFormat::Json => BoxMakeWriter::new(std::io::stderr),
+    };
     tracing_subscriber::registry()
-        .with(fmt::layer())
+        .with(fmt::layer().with_writer(writer))
         .with(EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info")))
         .init();
 }
@@ -1,91 +1,552 @@
-use crate::{cli::Cli, stats::Stats};
+use crate::{
+    cli::{Cli, DraftPolicy, FilenameDateMode, Precision, SectionMode, TimeZone},
+    error::Error,
+    explain::Explanation,
+    site::{is_bundle_file, is_section_file, ZolaSite},
+    stats::{FileRecord, FileStatus, SkipReason, Stats},
+};
 
-use anyhow::{bail, Context};
-use std::{fs, path::Path, process::Command};
+use anyhow::Context;
+use chrono::{Datelike, Timelike};
+use std::{
+    collections::BTreeSet,
+    fs,
+    path::{Path, PathBuf},
+};
 use tracing::{debug, error, trace, warn};
 
-use self::file_data::FileData;
+use self::{
+    file_data::{DateComparison, FileData},
+    filename_date::filename_date,
+    git_history::{history_folders, GitHistory},
+    path_filter::PathFilter,
+    repo_files::RepoFiles,
+    staged_files::StagedFiles,
+};
 mod file_data;
+mod filename_date;
+mod git_history;
+mod path_filter;
+mod repo_files;
+mod staged_files;
 
-pub fn walk_directory(root_path: &Path, cli: &Cli) -> anyhow::Result<Stats> {
+/// Everything needed to process files that stays the same for the whole run
+struct WalkContext<'a> {
+    cli: &'a Cli,
+    /// The folder (or file) being processed
+    root_path: &'a Path,
+    history: GitHistory,
+    today: toml_edit::Datetime,
+    comparison: DateComparison,
+    site: Option<ZolaSite>,
+    filter: PathFilter,
+    repo_files: RepoFiles,
+    /// Only set when processing staged files
+    staged: Option<StagedFiles>,
+}
+
+impl<'a> WalkContext<'a> {
+    fn new(root_path: &'a Path, cli: &'a Cli, site: Option<ZolaSite>) -> anyhow::Result<Self> {
+        let history = GitHistory::load(root_path, cli).context("Failed to load git history")?;
+        let mut filter_bases = vec![root_path.to_path_buf()];
+        filter_bases.extend(site.as_ref().map(|site| site.root().to_path_buf()));
+        let filter = PathFilter::new(cli, filter_bases)?;
+        let repo_files = RepoFiles::load(root_path, cli.tracked_only)
+            .context("Failed to list the files in the repository")?;
+        let staged = if cli.staged {
+            Some(StagedFiles::load(root_path).context("Failed to list the staged files")?)
+        } else {
+            None
+        };
+        // Read the clock only once so that all files agree on the date even if the run spans midnight
+        let now = now(cli);
+        debug!("Now is: {now}");
+        Ok(Self {
+            cli,
+            root_path,
+            history,
+            today: toml_datetime(now, cli.precision),
+            comparison: DateComparison {
+                precision: cli.precision.unwrap_or_default(),
+                time_zone: cli.timezone,
+            },
+            site,
+            filter,
+            repo_files,
+            staged,
+        })
+    }
+}
+
+pub fn walk_directory(
+    root_path: &Path,
+    cli: &Cli,
+    site: Option<ZolaSite>,
+) -> anyhow::Result<Stats> {
+    let context = WalkContext::new(root_path, cli, site)?;
+    match &context.staged {
+        Some(staged) => walk_staged(root_path, staged, &context),
+        None => walk(root_path, &context),
+    }
+}
+
+/// Applies the rules to the page at `path` without changing it and reports how each decision was made
+pub fn explain_file(path: &Path, cli: &Cli, site: Option<ZolaSite>) -> anyhow::Result<Explanation> {
+    let context = WalkContext::new(path, cli, site)?;
+    let mut result = Explanation {
+        path: path.to_path_buf(),
+        ..Default::default()
+    };
+    let is_section = is_section_path(path, context.site.as_ref());
+    result.skip_reason = skip_reason(path, is_section, cli, &context.filter, &context.repo_files);
+    if result.skip_reason.is_some() {
+        return Ok(result);
+    }
+    let mut data = FileData::new_from_path(path)?;
+    let source = history_source(path, is_section, &context);
+    if source != path {
+        result.history_source = Some(source.to_path_buf());
+    }
+    result.last_commit_date = context.history.last_edit(source).map(|dt| dt.to_rfc3339());
+    result.last_commit = context.history.last_edit_commit(source).map(String::from);
+    result.skip_reason = apply_rules(path, is_section, &mut data, &context, &mut result.notes)?;
+    result.rules = data.rule_trace().cloned();
+    Ok(result)
+}
+
+/// Returns the current time in the time zone selected or midnight of the date set by `--today`
+fn now(cli: &Cli) -> chrono::DateTime<chrono::FixedOffset> {
+    let now = chrono::Utc::now();
+    let Some(today) = cli.today else {
+        return match cli.timezone {
+            Some(time_zone) => time_zone.convert(&now),
+            None => now.with_timezone(&chrono::Local).fixed_offset(),
+        };
+    };
+    let midnight = today.and_time(chrono::NaiveTime::MIN);
+    let result = match cli.timezone {
+        Some(TimeZone::Iana(tz)) => midnight
+            .and_local_timezone(tz)
+            .earliest()
+            .map(|dt| dt.fixed_offset()),
+        Some(TimeZone::Fixed(offset)) => midnight.and_local_timezone(offset).earliest(),
+        None => midnight
+            .and_local_timezone(chrono::Local)
+            .earliest()
+            .map(|dt| dt.fixed_offset()),
+    };
+    // Midnight can be skipped by daylight saving, if so fall back to midnight in UTC
+    result.unwrap_or_else(|| midnight.and_utc().fixed_offset())
+}
+
+fn walk(root_path: &Path, context: &WalkContext) -> anyhow::Result<Stats> {
     let mut result = Stats::new();
     if root_path.is_file() {
-        match process_file(root_path, cli)
-            .with_context(|| format!("Processing failed for: {root_path:?}"))
-        {
-            Ok(stats) => result += stats,
-            Err(e) => {
-                error!("{e:?}");
-                result.inc_errors();
-            }
-        }
+        result.add_file(process_and_record(root_path, context));
     } else {
-        for entry in fs::read_dir(root_path)
-            .with_context(|| format!("Failed to read directory: {root_path:?}"))?
-        {
-            let entry =
-                entry.with_context(|| format!("Failed to extract a DirEntry in {root_path:?}"))?;
+        for entry in fs::read_dir(root_path).map_err(Error::io(root_path))? {
+            let entry = entry.map_err(Error::io(root_path))?;
             let path = entry.path();
-            result += walk_directory(&path, cli)?;
+            if path.is_dir() && !context.repo_files.should_visit_dir(&path) {
+                trace!("(Skipped Dir) {path:?}");
+                continue;
+            }
+            result += walk(&path, context)?;
         }
     }
 
     Ok(result)
 }
 
-fn process_file(path: &Path, cli: &Cli) -> anyhow::Result<Stats> {
+/// Processes the pages under `root_path` edited by the staged files then stages the changes made to them
+fn walk_staged(
+    root_path: &Path,
+    staged: &StagedFiles,
+    context: &WalkContext,
+) -> anyhow::Result<Stats> {
     let mut result = Stats::new();
-    if !should_skip_file(path) {
+    for path in staged_pages(root_path, staged, context)? {
+        let path = path.as_path();
+        if staged.has_unstaged_changes(path) {
+            let e = anyhow::anyhow!(
+                "{path:?} has changes that are not staged. Stage or stash them before running with `--staged`"
+            );
+            error!("{e}");
+            result.add_file(FileRecord::error(path.to_path_buf(), &e));
+        } else {
+            result.add_file(process_and_record(path, context));
+        }
+    }
+    if !context.cli.should_check_only {
+        let changed: Vec<&Path> = result
+            .files()
+            .iter()
+            .filter(|record| record.status == FileStatus::Changed)
+            .map(|record| record.path.as_path())
+            .collect();
+        staged
+            .stage(&changed)
+            .context("Failed to stage the changed files")?;
+    }
+    Ok(result)
+}
+
+/// Returns the staged Markdown files under `root_path` and the pages that count one of the staged files as an edit
+fn staged_pages(
+    root_path: &Path,
+    staged: &StagedFiles,
+    context: &WalkContext,
+) -> anyhow::Result<BTreeSet<PathBuf>> {
+    let mut result = BTreeSet::new();
+    for file in staged.files_under(root_path) {
+        if is_markdown(file) {
+            result.insert(file.to_path_buf());
+        }
+        // Sections and bundles can only count files in their own folder
+        for dir in history_folders(file) {
+            for entry in fs::read_dir(dir).map_err(Error::io(dir))? {
+                let page = entry.map_err(Error::io(dir))?.path();
+                if page == file || !page.starts_with(root_path) || !is_markdown(&page) {
+                    continue;
+                }
+                let is_section = is_section_path(&page, context.site.as_ref());
+                if history_filter(&page, is_section, context)
+                    .is_some_and(|is_included| is_included(file))
+                {
+                    result.insert(page);
+                }
+            }
+        }
+    }
+    Ok(result)
+}
+
+/// Processes a single file and records the outcome (Errors are logged and recorded instead of being returned)
+fn process_and_record(path: &Path, context: &WalkContext) -> FileRecord {
+    match process_file(path, context).with_context(|| format!("Processing failed for: {path:?}")) {
+        Ok(record) => record,
+        Err(e) => {
+            error!("{e:?}");
+            FileRecord::error(path.to_path_buf(), &e)
+        }
+    }
+}
+
+fn process_file(path: &Path, context: &WalkContext) -> anyhow::Result<FileRecord> {
+    let WalkContext {
+        cli,
+        root_path,
+        site,
+        filter,
+        repo_files,
+        ..
+    } = context;
+    let is_section = is_section_path(path, site.as_ref());
+    if let Some(reason) = skip_reason(path, is_section, cli, filter, repo_files) {
+        trace!("(Skipped)     {path:?} - {reason}");
+        Ok(FileRecord::skipped(path.to_path_buf(), reason))
+    } else {
         let mut data = FileData::new_from_path(path)?;
-        let last_edit_date =
-            get_git_last_edit_date(path).context("Failed to get last edit date from git")?;
-        data.update_front_matter(last_edit_date)
-            .context("Failed to update front_matter")?;
-        if data.is_changed() {
-            result.inc_changed();
+        let (old_date, old_updated) = data.date_values()?;
+        if let Some(reason) = apply_rules(path, is_section, &mut data, context, &mut vec![])? {
+            trace!("(Skipped)     {path:?} - {reason}");
+            return Ok(FileRecord::skipped(path.to_path_buf(), reason));
+        }
+        let status = if data.is_changed() {
             if cli.should_check_only {
                 warn!("(Change here) {path:?}");
             } else {
                 data.write().context("Failed to write to file")?;
                 trace!("(Changed)     {path:?}");
             }
+            FileStatus::Changed
         } else {
-            result.inc_not_changed();
             trace!("(Not Changed) {path:?}");
+            FileStatus::NotChanged
         };
+        let (new_date, new_updated) = data.date_values()?;
+        let diff = (cli.diff && data.is_changed()).then(|| {
+            // When processing a single file keep its name
+            let name = path
+                .strip_prefix(root_path)
+                .ok()
+                .filter(|name| !name.as_os_str().is_empty())
+                .or(path.file_name().map(Path::new))
+                .unwrap_or(path);
+            data.unified_diff(name)
+        });
+        Ok(FileRecord {
+            path: path.to_path_buf(),
+            status,
+            skip_reason: None,
+            old_date,
+            old_updated,
+            new_date,
+            new_updated,
+            error_kind: None,
+            error_chain: vec![],
+            diff,
+        })
+    }
+}
+
+/// Updates the dates in `data` (the page at `path`) following the rules and the settings
+///
+/// Returns the reason if the page should be skipped instead. Steps taken before the rules are applied are added to `notes`.
+fn apply_rules(
+    path: &Path,
+    is_section: bool,
+    data: &mut FileData,
+    context: &WalkContext,
+    notes: &mut Vec<String>,
+) -> anyhow::Result<Option<SkipReason>> {
+    let WalkContext {
+        cli,
+        history,
+        today,
+        comparison,
+        ..
+    } = context;
+    data.set_comparison(*comparison);
+    let is_draft = data.is_draft()?;
+    match cli.drafts.unwrap_or_default() {
+        DraftPolicy::Skip if is_draft => return Ok(Some(SkipReason::Draft)),
+        DraftPolicy::Defer if is_draft => {
+            notes.push("Draft with `--drafts defer`: `date` and `updated` removed".to_string());
+            data.clear_dates()?;
+        }
+        DraftPolicy::Defer
+            if !data.has_date()?
+                && (cli.filename_date == Some(FilenameDateMode::Ignore)
+                    || filename_date(path).is_none())
+                && is_just_published(path, context)? =>
+        {
+            // Published since the last commit, it goes live today unless the file name says otherwise
+            notes.push(
+                "No `date` with `--drafts defer` and was a draft (or never committed) in the last commit: treated as published today (Git history not used)"
+                    .to_string(),
+            );
+            data.update_front_matter(None, None, *today)
+                .context("Failed to update front_matter")?;
+        }
+        _ => {
+            let last_edit_date = if is_staged_edit(path, is_section, context) {
+                notes.push(
+                    "Staged (or a file counted as its edit is) with `--staged`: last edit treated as today"
+                        .to_string(),
+                );
+                Some(*today)
+            } else {
+                history
+                    .last_edit(history_source(path, is_section, context))
+                    .map(|dt| toml_datetime(dt, cli.precision))
+            };
+            let first_commit_date = if cli.date_from_first_commit {
+                history
+                    .first_commit(path)
+                    .map(|dt| toml_datetime(dt, cli.precision))
+            } else {
+                None
+            };
+            let filename_date = apply_filename_date(path, data, cli, notes)?;
+            data.update_front_matter(last_edit_date, filename_date.or(first_commit_date), *today)
+                .context("Failed to update front_matter")?;
+        }
+    }
+    Ok(None)
+}
+
+/// Returns the file whose history gives the last edit of the page at `path` (Differs from `path` for sections and bundles depending on the settings)
+fn history_source<'a>(path: &'a Path, is_section: bool, context: &'a WalkContext) -> &'a Path {
+    let Some(is_included) = history_filter(path, is_section, context) else {
+        return path;
+    };
+    path.parent()
+        .and_then(|dir| context.history.newest_file_in(dir, is_included))
+        .unwrap_or(path)
+}
+
+/// Accepts the files that count as edits of a page
+type HistoryFilter<'a> = Box<dyn Fn(&Path) -> bool + 'a>;
+
+/// Returns which files in its folder count as edits of the page at `path` (None if only the page itself does)
+///
+/// Files in the folder are the ones counting towards its history (See [`history_folders`]).
+fn history_filter<'a>(
+    path: &'a Path,
+    is_section: bool,
+    context: &'a WalkContext,
+) -> Option<HistoryFilter<'a>> {
+    let WalkContext { cli, site, .. } = context;
+    if is_section && cli.sections == Some(SectionMode::Children) {
+        Some(Box::new(|child| {
+            is_markdown(child) && !is_section_path(child, site.as_ref())
+        }))
+    } else if cli.bundle_assets && is_bundle_file(path) {
+        // Pages for other languages in the same bundle are not assets
+        Some(Box::new(move |file| file == path || !is_bundle_file(file)))
     } else {
-        result.inc_skipped();
-        trace!("(Skipped)     {path:?}");
+        None
     }
-    Ok(result)
 }
 
-fn get_git_last_edit_date(path: &Path) -> anyhow::Result<Option<toml_edit::Date>> {
-    let output = Command::new("git")
-        .args(["log", "-1", "--format=%cs", path.to_string_lossy().as_ref()])
-        .output()
-        .context("Failed to execute git command")?;
-    if !output.status.success() || !output.stderr.is_empty() {
-        bail!(
-            "Running git failed. status: {} stdout: {}, stderr: {}",
-            output.status,
-            std::str::from_utf8(&output.stdout)?,
-            std::str::from_utf8(&output.stderr)?
-        );
+/// Checks if the page at `path` or one of the files counted as its edits is staged (Only set with `--staged`)
+fn is_staged_edit(path: &Path, is_section: bool, context: &WalkContext) -> bool {
+    let Some(staged) = &context.staged else {
+        return false;
+    };
+    if staged.contains(path) {
+        return true;
+    }
+    let (Some(dir), Some(is_included)) = (path.parent(), history_filter(path, is_section, context))
+    else {
+        return false;
+    };
+    staged.files_in(dir).any(is_included)
+}
+
+/// Checks if the page at `path` was never committed or was a draft in the last commit (So it is being published now)
+fn is_just_published(path: &Path, context: &WalkContext) -> anyhow::Result<bool> {
+    if context.history.last_edit(path).is_none() {
+        return Ok(true);
+    }
+    let Some(content) = git_history::committed_content(path)
+        .with_context(|| format!("Failed to read the last commit of {path:?}"))?
+    else {
+        return Ok(true);
+    };
+    // A committed version that cannot be parsed is not evidence that it was a draft
+    Ok(FileData::new_from_str(path, &content)
+        .and_then(|committed| committed.is_draft())
+        .unwrap_or(false))
+}
+
+/// Checks `date` against the date in the file name (if any) according to the mode set and returns the date from the file name
+fn apply_filename_date(
+    path: &Path,
+    data: &mut FileData,
+    cli: &Cli,
+    notes: &mut Vec<String>,
+) -> anyhow::Result<Option<toml_edit::Datetime>> {
+    let mode = cli.filename_date.unwrap_or_default();
+    if mode == FilenameDateMode::Ignore {
+        return Ok(None);
+    }
+    let Some(expected) = filename_date(path) else {
+        return Ok(None);
+    };
+    notes.push(format!("File name has the date {expected}"));
+    match data.date()? {
+        Some(actual) if actual.date != expected.date => {
+            if mode == FilenameDateMode::Fix {
+                notes.push(format!(
+                    "`date` ({actual}) replaced by the date in the file name"
+                ));
+                data.set_date(expected)?;
+            } else {
+                notes.push(format!(
+                    "`date` ({actual}) does not match the date in the file name"
+                ));
+                warn!("(Date mismatch) {path:?} - `date` is {actual} but the file name has {expected}");
+            }
+        }
+        _ => {}
     }
-    let stdout = std::str::from_utf8(&output.stdout)?;
-    debug!("GitDate: {:?} - {path:?}", stdout.trim());
+    Ok(Some(expected))
+}
 
-    if stdout.is_empty() {
-        Ok(None)
+fn skip_reason(
+    path: &Path,
+    is_section: bool,
+    cli: &Cli,
+    filter: &PathFilter,
+    repo_files: &RepoFiles,
+) -> Option<SkipReason> {
+    if !is_markdown(path) {
+        return Some(SkipReason::NotMarkdown);
+    }
+    if let Some(reason) = repo_files.skip_reason(path) {
+        Some(reason)
+    } else if is_section && cli.sections.unwrap_or_default() == SectionMode::Skip {
+        Some(SkipReason::SectionFile)
     } else {
-        let year: u16 = stdout[..=3].parse().context("Failed to parse year")?;
-        let month: u8 = stdout[5..=6].parse().context("Failed to parse month")?;
-        let day: u8 = stdout[8..=9].parse().context("Failed to parse day")?;
-        Ok(Some(toml_edit::Date { year, month, day }))
+        filter.skip_reason(path)
     }
 }
 
-fn should_skip_file(path: &Path) -> bool {
-    path.extension().is_none_or(|ext| ext != "md") || path.ends_with("_index.md")
+fn is_markdown(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext == "md")
+}
+
+fn is_section_path(path: &Path, site: Option<&ZolaSite>) -> bool {
+    match site {
+        Some(site) => site.is_section_file(path),
+        None => is_section_file(path, &[]),
+    }
+}
+
+/// Converts `dt` into a TOML datetime keeping only the parts needed for `precision` (Defaults to only the date)
+fn toml_datetime(
+    dt: chrono::DateTime<chrono::FixedOffset>,
+    precision: Option<Precision>,
+) -> toml_edit::Datetime {
+    let date = Some(toml_edit::Date {
+        year: dt.year() as _,
+        month: dt.month() as _,
+        day: dt.day() as _,
+    });
+    let time = Some(toml_edit::Time {
+        hour: dt.hour() as _,
+        minute: dt.minute() as _,
+        second: dt.second() as _,
+        nanosecond: 0,
+    });
+    let offset = match dt.offset().local_minus_utc() / 60 {
+        0 => toml_edit::Offset::Z,
+        minutes => toml_edit::Offset::Custom {
+            minutes: minutes as _,
+        },
+    };
+    match precision.unwrap_or_default() {
+        Precision::Date => toml_edit::Datetime {
+            date,
+            time: None,
+            offset: None,
+        },
+        Precision::LocalDatetime => toml_edit::Datetime {
+            date,
+            time,
+            offset: None,
+        },
+        Precision::OffsetDatetime => toml_edit::Datetime {
+            date,
+            time,
+            offset: Some(offset),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case(None, "2023-05-01")]
+    #[case(Some(Precision::Date), "2023-05-01")]
+    #[case(Some(Precision::LocalDatetime), "2023-05-01T10:20:30")]
+    #[case(Some(Precision::OffsetDatetime), "2023-05-01T10:20:30+02:00")]
+    fn datetime_precision(#[case] precision: Option<Precision>, #[case] expected: &str) {
+        let dt = chrono::DateTime::parse_from_rfc3339("2023-05-01T10:20:30.5+02:00").unwrap();
+        assert_eq!(toml_datetime(dt, precision).to_string(), expected);
+    }
+
+    #[test]
+    fn datetime_utc_offset() {
+        let dt = chrono::DateTime::parse_from_rfc3339("2023-05-01T10:20:30+00:00").unwrap();
+        assert_eq!(
+            toml_datetime(dt, Some(Precision::OffsetDatetime)).to_string(),
+            "2023-05-01T10:20:30Z"
+        );
+    }
 }
@@ -1,12 +1,20 @@
-use std::{fs, io::Write, path::Path};
+use std::{cmp::Ordering, fs, io::Write, path::Path};
 
 use anyhow::{bail, Context};
-use chrono::Datelike;
+use chrono::TimeZone as _;
 use once_cell::sync::Lazy;
 use regex::Regex;
 use toml_edit::DocumentMut;
 use tracing::warn;
 
+use self::yaml::YamlFrontMatter;
+use crate::{
+    cli::{Precision, TimeZone},
+    error::Error,
+    explain::RuleTrace,
+};
+mod yaml;
+
 static TOML_RE: Lazy<Regex> = Lazy::new(|| {
     Regex::new(
         r"^[[:space:]]*\+\+\+(\r?\n(?s).*?(?-s))\+\+\+[[:space:]]*(?:$|(?:\r?\n((?s).*(?-s))$))",
@@ -14,20 +22,227 @@ static TOML_RE: Lazy<Regex> = Lazy::new(|| {
     .unwrap()
 });
 
-static TODAY: Lazy<toml_edit::Item> = Lazy::new(|| {
-    let now = chrono::Local::now();
-    item_from_date(toml_edit::Date {
-        year: now.year() as _,
-        month: now.month() as _,
-        day: now.day() as _,
-    })
+static YAML_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"^[[:space:]]*---(\r?\n(?s).*?(?-s))---[[:space:]]*(?:$|(?:\r?\n((?s).*(?-s))$))")
+        .unwrap()
 });
 
+/// The formats of front matter supported by zola
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrontMatterFormat {
+    Toml,
+    Yaml,
+}
+
+impl FrontMatterFormat {
+    fn delimiter(&self) -> &'static str {
+        match self {
+            FrontMatterFormat::Toml => "+++",
+            FrontMatterFormat::Yaml => "---",
+        }
+    }
+}
+
+/// Parsed front matter that allows top level keys to be read and updated while preserving the rest of the document
+enum FrontMatter {
+    Toml(DocumentMut),
+    Yaml(YamlFrontMatter),
+}
+
+impl FrontMatter {
+    fn parse(format: FrontMatterFormat, s: &str, path: &Path) -> Result<Self, Error> {
+        Ok(match format {
+            FrontMatterFormat::Toml => {
+                Self::Toml(
+                    s.parse::<DocumentMut>()
+                        .map_err(|source| Error::InvalidToml {
+                            path: path.to_path_buf(),
+                            source,
+                        })?,
+                )
+            }
+            FrontMatterFormat::Yaml => Self::Yaml(YamlFrontMatter::parse(s).map_err(
+                |message| Error::InvalidYaml {
+                    path: path.to_path_buf(),
+                    message,
+                },
+            )?),
+        })
+    }
+
+    fn get(&self, key: &str) -> Option<toml_edit::Item> {
+        match self {
+            FrontMatter::Toml(doc) => doc.get(key).cloned(),
+            FrontMatter::Yaml(doc) => doc.get(key),
+        }
+    }
+
+    fn set(&mut self, key: &str, value: toml_edit::Item) {
+        match self {
+            FrontMatter::Toml(doc) => match doc.entry(key) {
+                toml_edit::Entry::Occupied(mut entry) => *entry.get_mut() = value,
+                toml_edit::Entry::Vacant(entry) => {
+                    entry.insert(value);
+                }
+            },
+            FrontMatter::Yaml(doc) => doc.set(key, &value),
+        }
+    }
+
+    fn remove(&mut self, key: &str) {
+        match self {
+            FrontMatter::Toml(doc) => {
+                doc.remove(key);
+            }
+            FrontMatter::Yaml(doc) => doc.remove(key),
+        }
+    }
+}
+
+impl std::fmt::Display for FrontMatter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FrontMatter::Toml(doc) => write!(f, "{doc}"),
+            FrontMatter::Yaml(doc) => write!(f, "{doc}"),
+        }
+    }
+}
+
+/// How the rules compare values of `date` and `updated`
+///
+/// Values with an offset are converted to the time zone to find their day and values with a time but no offset are taken
+/// to be in it. Without a time zone the day is as written and the local time zone is used for values without an offset.
+/// Comparisons with today are always by day as today may be midnight (See `--today`).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DateComparison {
+    /// With [`Precision::Date`] values are compared by day, otherwise values that both have a time are compared as instants
+    pub precision: Precision,
+    /// See `--timezone`
+    pub time_zone: Option<TimeZone>,
+}
+
+impl DateComparison {
+    /// Checks if the precision includes a time (So values set by the tool can match a commit exactly)
+    fn has_time(&self) -> bool {
+        self.precision != Precision::Date
+    }
+
+    /// Orders `a` and `b` by instant if the precision allows it and both have a time, otherwise by day
+    ///
+    /// Returns None if either is not a date.
+    fn compare(&self, a: &toml_edit::Item, b: &toml_edit::Item) -> Option<Ordering> {
+        let (a_day, a_instant) = self.normalize(a)?;
+        let (b_day, b_instant) = self.normalize(b)?;
+        match (self.precision, a_instant, b_instant) {
+            (Precision::LocalDatetime | Precision::OffsetDatetime, Some(a), Some(b)) => {
+                Some(a.cmp(&b))
+            }
+            _ => Some(a_day.cmp(&b_day)),
+        }
+    }
+
+    /// Orders `a` and `b` by day only
+    fn compare_days(&self, a: &toml_edit::Item, b: &toml_edit::Item) -> Option<Ordering> {
+        Some(self.normalize(a)?.0.cmp(&self.normalize(b)?.0))
+    }
+
+    /// Returns the day of `item` in the time zone and the instant it refers to (if it has a time)
+    ///
+    /// Returns None if `item` is not a date.
+    fn normalize(
+        &self,
+        item: &toml_edit::Item,
+    ) -> Option<(
+        chrono::NaiveDate,
+        Option<chrono::DateTime<chrono::FixedOffset>>,
+    )> {
+        let dt = item.as_datetime()?;
+        let date = dt.date?;
+        let date =
+            chrono::NaiveDate::from_ymd_opt(date.year.into(), date.month.into(), date.day.into())?;
+        let Some(time) = dt.time else {
+            return Some((date, None));
+        };
+        let naive = date.and_time(chrono::NaiveTime::from_hms_nano_opt(
+            time.hour.into(),
+            time.minute.into(),
+            time.second.into(),
+            time.nanosecond,
+        )?);
+        let instant = match dt.offset {
+            Some(offset) => {
+                let seconds = match offset {
+                    toml_edit::Offset::Z => 0,
+                    toml_edit::Offset::Custom { minutes } => i32::from(minutes) * 60,
+                };
+                chrono::FixedOffset::east_opt(seconds)?
+                    .from_local_datetime(&naive)
+                    .single()?
+            }
+            None => match self.time_zone {
+                Some(TimeZone::Iana(tz)) => {
+                    tz.from_local_datetime(&naive).earliest()?.fixed_offset()
+                }
+                Some(TimeZone::Fixed(offset)) => offset.from_local_datetime(&naive).single()?,
+                None => chrono::Local
+                    .from_local_datetime(&naive)
+                    .earliest()?
+                    .fixed_offset(),
+            },
+        };
+        let day = match (self.time_zone, dt.offset) {
+            (Some(time_zone), Some(_)) => time_zone.date_of(&instant),
+            _ => date,
+        };
+        Some((day, Some(instant)))
+    }
+
+    /// Checks if both a and b are dates and if a < b
+    fn is_less_than_date(&self, a: &toml_edit::Item, b: &toml_edit::Item) -> bool {
+        self.compare(a, b) == Some(Ordering::Less)
+    }
+
+    /// Check if both a and b are dates and if a <= b
+    fn is_less_than_or_equal_date(&self, a: &toml_edit::Item, b: &toml_edit::Item) -> bool {
+        matches!(self.compare(a, b), Some(Ordering::Less | Ordering::Equal))
+    }
+
+    /// Check if both a and b are dates and a == b
+    fn is_equal_date(&self, a: &toml_edit::Item, b: &toml_edit::Item) -> bool {
+        self.compare(a, b) == Some(Ordering::Equal)
+    }
+
+    /// Checks if both a and b are dates and if a is on an earlier day than b
+    fn is_less_than_day(&self, a: &toml_edit::Item, b: &toml_edit::Item) -> bool {
+        self.compare_days(a, b) == Some(Ordering::Less)
+    }
+
+    /// Checks if both a and b are dates and if a is on the same day as b or before it
+    fn is_less_than_or_equal_day(&self, a: &toml_edit::Item, b: &toml_edit::Item) -> bool {
+        matches!(
+            self.compare_days(a, b),
+            Some(Ordering::Less | Ordering::Equal)
+        )
+    }
+
+    /// Checks if both a and b are dates on the same day
+    fn is_equal_day(&self, a: &toml_edit::Item, b: &toml_edit::Item) -> bool {
+        self.compare_days(a, b) == Some(Ordering::Equal)
+    }
+}
+
 pub struct FileData<'a> {
     is_changed: bool,
     path: &'a Path,
+    format: FrontMatterFormat,
     front_matter: String,
     content: String,
+    /// The text of the file as it was read (Empty if not read from text)
+    original: String,
+    /// How the rules were applied by the last call to [`Self::update_front_matter`]
+    rule_trace: Option<RuleTrace>,
+    /// How values are compared by the rules
+    comparison: DateComparison,
 }
 
 impl<'a> FileData<'a> {
@@ -41,62 +256,108 @@ impl<'a> FileData<'a> {
         let mut file = fs::OpenOptions::new()
             .write(true)
             .truncate(true)
-            .open(self.path)?;
-        let mut s = "+++".to_string();
+            .open(self.path)
+            .map_err(Error::io(self.path))?;
+        file.write_all(self.to_file_string().as_bytes())
+            .map_err(Error::io(self.path))?;
+        Ok(())
+    }
+
+    /// Builds the full text of the file (front matter and content)
+    fn to_file_string(&self) -> String {
+        let delimiter = self.format.delimiter();
+        let mut s = delimiter.to_string();
         s.push_str(&self.front_matter);
-        s.push_str("+++\n");
+        s.push_str(delimiter);
+        s.push('\n');
         if !self.content.is_empty() {
             // Added a space between to match `dprint`
             s.push('\n');
         }
         s.push_str(&self.content);
-        file.write_all(s.as_bytes())?;
-        Ok(())
+        s
+    }
+
+    /// Builds a unified diff between the file as it was read and the text [`Self::write`] would write (`name` is used in the headers)
+    pub fn unified_diff(&self, name: &Path) -> String {
+        let path = name.display();
+        similar::TextDiff::from_lines(&self.original, &self.to_file_string())
+            .unified_diff()
+            .header(&format!("a/{path}"), &format!("b/{path}"))
+            .to_string()
+    }
+
+    /// Sets how values are compared by [`Self::update_front_matter`]
+    pub fn set_comparison(&mut self, comparison: DateComparison) {
+        self.comparison = comparison;
     }
 
     /// See cli::Cli command.long for explanation of rules (or readme)
+    ///
+    /// If `fallback_date` is provided it is used as `date` when `date` is missing or invalid (eg. the date of the first commit or the date in the file name).
+    /// `today` is the date the rules treat as the current date.
+    /// Values are compared as set by [`Self::set_comparison`] and are written with as much precision as they are passed in.
     pub fn update_front_matter(
         &mut self,
-        last_edit_date: Option<toml_edit::Date>,
+        last_edit_date: Option<toml_edit::Datetime>,
+        fallback_date: Option<toml_edit::Datetime>,
+        today: toml_edit::Datetime,
     ) -> anyhow::Result<()> {
         let key_date = "date";
         let key_updated = "updated";
-        let toml = &self.front_matter[..];
-        let mut doc = toml
-            .parse::<DocumentMut>()
-            .context("Failed to parse TOML in front matter")?;
-        debug_assert_eq!(doc.to_string(), toml);
+        let mut doc = FrontMatter::parse(self.format, &self.front_matter, self.path)?;
+        debug_assert_eq!(doc.to_string(), self.front_matter);
         let org_date = doc.get(key_date);
         let org_updated = doc.get(key_updated);
+        let org_date = org_date.as_ref();
+        let org_updated = org_updated.as_ref();
 
-        if last_edit_date.is_some()
-            && is_less_than_date(&TODAY, &item_from_date(last_edit_date.unwrap()))
-        {
-            bail!("Got a LAST edit date in the future...? We think today is: {} and last edit date found is {} for path {:?}", 
-                date_to_display(Some(&TODAY)),
-                date_to_display(Some(&item_from_date(last_edit_date.unwrap()))),
-                self.path
-            )
+        if let Some(last_edit_date) = last_edit_date {
+            if self.comparison.is_less_than_day(
+                &item_from_datetime(today),
+                &item_from_datetime(last_edit_date),
+            ) {
+                return Err(Error::FutureCommitDate {
+                    path: self.path.to_path_buf(),
+                    today: date_to_display(Some(&item_from_datetime(today)))?,
+                    last_edit: date_to_display(Some(&item_from_datetime(last_edit_date)))?,
+                }
+                .into());
+            }
         }
 
-        let (new_date, new_updated) =
-            self.calculate_new_date_and_updated(org_date, org_updated, last_edit_date);
+        let mut fixups = vec![];
+        let (new_date, new_updated, rule) = self.calculate_new_date_and_updated(
+            org_date,
+            org_updated,
+            last_edit_date,
+            fallback_date,
+            today,
+            &mut fixups,
+        );
+        self.rule_trace = Some(RuleTrace {
+            org_date: org_date.map(item_to_display),
+            org_updated: org_updated.map(item_to_display),
+            last_edit_date: last_edit_date.map(|d| d.to_string()),
+            fallback_date: fallback_date.map(|d| d.to_string()),
+            today: today.to_string(),
+            fixups,
+            rule,
+            new_date: item_to_display(&new_date),
+            new_updated: new_updated.as_ref().map(item_to_display),
+        });
 
-        if !is_new_same_as_org(org_date, org_updated, &new_date, &new_updated) {
+        if !is_new_same_as_org(
+            &self.comparison,
+            org_date,
+            org_updated,
+            &new_date,
+            &new_updated,
+        ) {
             self.is_changed = true;
-            match doc.entry(key_date) {
-                toml_edit::Entry::Occupied(mut entry) => *entry.get_mut() = new_date,
-                toml_edit::Entry::Vacant(entry) => {
-                    entry.insert(new_date);
-                }
-            }
+            doc.set(key_date, new_date);
             if let Some(nu) = new_updated {
-                match doc.entry(key_updated) {
-                    toml_edit::Entry::Occupied(mut entry) => *entry.get_mut() = nu,
-                    toml_edit::Entry::Vacant(entry) => {
-                        entry.insert(nu);
-                    }
-                }
+                doc.set(key_updated, nu);
             } else {
                 doc.remove(key_updated);
             }
@@ -106,58 +367,162 @@ impl<'a> FileData<'a> {
         Ok(())
     }
 
+    /// How the rules were applied by the last call to [`Self::update_front_matter`] (None if not called)
+    pub fn rule_trace(&self) -> Option<&RuleTrace> {
+        self.rule_trace.as_ref()
+    }
+
+    /// Checks if the page is marked as a draft (`draft = true`)
+    pub fn is_draft(&self) -> anyhow::Result<bool> {
+        let doc = FrontMatter::parse(self.format, &self.front_matter, self.path)?;
+        Ok(doc
+            .get("draft")
+            .and_then(|item| item.as_bool())
+            .unwrap_or_default())
+    }
+
+    /// Checks if the front matter has a `date` (Valid or not)
+    pub fn has_date(&self) -> anyhow::Result<bool> {
+        let doc = FrontMatter::parse(self.format, &self.front_matter, self.path)?;
+        Ok(doc.get("date").is_some())
+    }
+
+    /// Returns the values of `date` and `updated` as written in the front matter
+    pub fn date_values(&self) -> anyhow::Result<(Option<String>, Option<String>)> {
+        let doc = FrontMatter::parse(self.format, &self.front_matter, self.path)?;
+        let value = |key| doc.get(key).as_ref().map(item_to_display);
+        Ok((value("date"), value("updated")))
+    }
+
+    /// Returns `date` from the front matter if it is set to a date
+    pub fn date(&self) -> anyhow::Result<Option<toml_edit::Datetime>> {
+        let doc = FrontMatter::parse(self.format, &self.front_matter, self.path)?;
+        Ok(doc
+            .get("date")
+            .and_then(|item| item.as_datetime().copied())
+            .filter(|d| d.date.is_some()))
+    }
+
+    /// Sets `date` in the front matter (Only marked as changed if the day is different)
+    pub fn set_date(&mut self, date: toml_edit::Datetime) -> anyhow::Result<()> {
+        let mut doc = FrontMatter::parse(self.format, &self.front_matter, self.path)?;
+        let new_date = item_from_datetime(date);
+        if doc
+            .get("date")
+            .is_some_and(|org_date| self.comparison.is_equal_day(&org_date, &new_date))
+        {
+            return Ok(());
+        }
+        doc.set("date", new_date);
+        self.is_changed = true;
+        self.front_matter = doc.to_string();
+        Ok(())
+    }
+
+    /// Removes `date` and `updated` from the front matter (Only marked as changed if either was set)
+    pub fn clear_dates(&mut self) -> anyhow::Result<()> {
+        let mut doc = FrontMatter::parse(self.format, &self.front_matter, self.path)?;
+        let mut is_changed = false;
+        for key in ["date", "updated"] {
+            if doc.get(key).is_some() {
+                doc.remove(key);
+                is_changed = true;
+            }
+        }
+        if is_changed {
+            self.is_changed = true;
+            self.front_matter = doc.to_string();
+        }
+        Ok(())
+    }
+
     fn calculate_new_date_and_updated(
         &self,
-        mut date: Option<&toml_edit::Item>,
-        mut updated: Option<&toml_edit::Item>,
-        last_edit_date: Option<toml_edit::Date>,
-    ) -> (toml_edit::Item, Option<toml_edit::Item>) {
+        date: Option<&toml_edit::Item>,
+        updated: Option<&toml_edit::Item>,
+        last_edit_date: Option<toml_edit::Datetime>,
+        fallback_date: Option<toml_edit::Datetime>,
+        today: toml_edit::Datetime,
+        fixups: &mut Vec<String>,
+    ) -> (toml_edit::Item, Option<toml_edit::Item>, &'static str) {
+        let today = &item_from_datetime(today);
+        let comparison = &self.comparison;
+        let mut fixup = |msg: &str| {
+            warn!("{msg} in {:?}", self.path);
+            fixups.push(msg.to_string());
+        };
         assert!(
             last_edit_date.is_none()
-                || is_less_than_or_equal_date(&item_from_date(last_edit_date.unwrap()), &TODAY),
+                || comparison.is_less_than_or_equal_day(&item_from_datetime(last_edit_date.unwrap()), today),
                 "Precondition to call this function is that `last_edit_date` must be today or in the past"
         );
+        // With a time `updated` is set to the last commit so that it matches it exactly (Today is the time of the run)
+        let last_edit = last_edit_date
+            .filter(|_| comparison.has_time())
+            .map(item_from_datetime);
+        let edit_time = last_edit.as_ref().unwrap_or(today);
+        let set_to = |day_rule, time_rule| {
+            if last_edit.is_some() {
+                time_rule
+            } else {
+                day_rule
+            }
+        };
+        let fallback_date = fallback_date.map(item_from_datetime);
+        let mut date = date;
+        let mut updated = updated;
+        // The rule to report when a fixup replaced `updated` (The rules below then keep the replacement)
+        let mut updated_fixup_rule = None;
         // Check for wrong type
         if let Some(d) = date {
-            if !d.is_datetime() {
-                warn!("Non date value found for `date` in {:?}", self.path);
+            if !is_date(d) {
+                fixup("Non date value found for `date`");
                 date = None; // Only allow dates
             }
         }
+        if date.is_none() {
+            date = fallback_date.as_ref(); // Backfill if available
+        }
         if let Some(u) = updated {
-            if !u.is_datetime() {
-                warn!("Non date value found for `updated` in {:?}", self.path);
-                updated = Some(&TODAY); // Only allow dates (Must be updated now)
+            if !is_date(u) {
+                fixup("Non date value found for `updated`");
+                updated = Some(edit_time); // Only allow dates (Must be updated now)
+                updated_fixup_rule = Some(set_to(
+                    "`updated` is not a date: `updated` set to today",
+                    "`updated` is not a date: `updated` set to the last commit",
+                ));
             }
         }
 
         // Ensure if updated exists it is greater than or equal to date otherwise discard value
         if let Some(updated_date) = updated {
             if let Some(date) = date {
-                if is_less_than_date(updated_date, date) {
-                    warn!("`updated` is before `date` but this should never happen. `updated` being ignored in {:?}", self.path);
-                    updated = Some(&TODAY); // Must be updated now
+                if comparison.is_less_than_date(updated_date, date) {
+                    fixup("`updated` is before `date` but this should never happen. `updated` being ignored");
+                    updated = Some(edit_time); // Must be updated now
+                    updated_fixup_rule = Some(set_to(
+                        "`updated` is before `date`: `updated` set to today",
+                        "`updated` is before `date`: `updated` set to the last commit",
+                    ));
                 }
             }
         }
 
         // Clear date if it is in the future
         if let Some(curr_date) = date {
-            if is_less_than_date(&TODAY, curr_date) {
-                warn!(
-                    "date is set in the future. Date is being ignored in {:?}",
-                    self.path
-                );
+            if comparison.is_less_than_day(today, curr_date) {
+                fixup("date is set in the future. Date is being ignored");
                 date = None;
             }
         }
         if let Some(curr_updated) = updated {
-            if is_less_than_date(&TODAY, curr_updated) {
-                warn!(
-                    "updated is set in the future. updated is being ignored in {:?}",
-                    self.path
-                );
-                updated = Some(&TODAY); // Must be updated now
+            if comparison.is_less_than_day(today, curr_updated) {
+                fixup("updated is set in the future. updated is being ignored");
+                updated = Some(edit_time); // Must be updated now
+                updated_fixup_rule = Some(set_to(
+                    "`updated` is in the future: `updated` set to today",
+                    "`updated` is in the future: `updated` set to the last commit",
+                ));
             }
         }
 
@@ -165,76 +530,135 @@ impl<'a> FileData<'a> {
         // If changing to a date, prefer copying original value cuz dates created do not include times nor offset
         // Assumptions are documented here but are enforced above. Documented here for ease of reference and not repeated below.
         debug_assert!(
-            date.is_none() || is_less_than_or_equal_date(date.unwrap(), &TODAY),
+            date.is_none() || comparison.is_less_than_or_equal_day(date.unwrap(), today),
             "ASSUMPTION FAILED. Expected: `date` if set to be today or in the past"
         );
         debug_assert!(
-            updated.is_none() || is_less_than_or_equal_date(updated.unwrap(), &TODAY),
+            updated.is_none() || comparison.is_less_than_or_equal_day(updated.unwrap(), today),
             "ASSUMPTION FAILED. Expected: `updated` if set to be today or in the past"
         );
         debug_assert!(
             date.is_none()
                 || updated.is_none()
-                || is_less_than_or_equal_date(date.unwrap(), updated.unwrap()),
+                || comparison.is_less_than_or_equal_day(date.unwrap(), updated.unwrap()),
             "ASSUMPTION FAILED. Expected: date <= updated"
         );
-        let (new_date, new_updated) = match (last_edit_date, date, updated) {
+        let (new_date, new_updated, rule) = match (last_edit_date, date, updated) {
             (None, None, _) => {
                 // No dates, set `date` to TODAY clearing updated if it's set
-                (TODAY.clone(), None)
+                (
+                    today.clone(),
+                    None,
+                    "Never committed and no `date`: `date` set to today and `updated` removed",
+                )
             }
             (None, Some(date), _) => {
                 // This file has never been committed but has `date`
-                if is_equal_date(date, &TODAY) {
+                if comparison.is_equal_day(date, today) {
                     // `date` is TODAY, clear updated if it's set
-                    (date.clone(), None)
+                    (
+                        date.clone(),
+                        None,
+                        "Never committed and `date` is today: `updated` removed",
+                    )
                 } else {
                     // Keep existing `date`. `updated` becomes TODAY
-                    (date.clone(), Some(TODAY.clone()))
+                    (
+                        date.clone(),
+                        Some(today.clone()),
+                        "Never committed and `date` is before today: `updated` set to today",
+                    )
                 }
             }
             (Some(last), None, _) => {
                 // Previously committed but no dates set
-                let last = item_from_date(last);
-                if is_equal_date(&last, &TODAY) {
-                    (last, None)
+                let last = item_from_datetime(last);
+                if comparison.is_equal_day(&last, today) {
+                    (
+                        last,
+                        None,
+                        "No `date` and last commit is today: `date` set to the last commit date and `updated` removed",
+                    )
+                } else if last_edit.is_some() {
+                    // `updated` would be the same as `date`
+                    (
+                        last,
+                        None,
+                        "No `date`: `date` set to the last commit and `updated` removed",
+                    )
                 } else {
-                    (last, Some(TODAY.clone()))
+                    (
+                        last,
+                        Some(today.clone()),
+                        "No `date` and last commit is before today: `date` set to the last commit date and `updated` set to today",
+                    )
                 }
             }
             (Some(last), Some(date), None) => {
                 // Previously committed check and `date` set. Set updated only if needed (ie. `date` < `last`)
-                let last = item_from_date(last);
-                if is_less_than_or_equal_date(&last, date) {
-                    (date.clone(), None)
+                let last = item_from_datetime(last);
+                if comparison.is_less_than_or_equal_date(&last, date) {
+                    (
+                        date.clone(),
+                        None,
+                        "No `updated` and last commit is on or before `date`: no update needed",
+                    )
                 } else {
                     // `date` < `last` need to set `updated`
-                    (date.clone(), Some(TODAY.clone()))
+                    (
+                        date.clone(),
+                        Some(edit_time.clone()),
+                        set_to(
+                            "No `updated` and last commit is after `date`: `updated` set to today",
+                            "No `updated` and last commit is after `date`: `updated` set to the last commit",
+                        ),
+                    )
                 }
             }
             (Some(last), Some(date), Some(updated)) => {
                 // All 3 dates set
-                let last = item_from_date(last);
-                if is_equal_date(date, &TODAY) {
-                    (date.clone(), None)
-                } else if is_less_than_or_equal_date(&last, updated) {
-                    // Values are fine, keep same
-                    (date.clone(), Some(updated.clone()))
+                let last = item_from_datetime(last);
+                if comparison.is_equal_day(date, today) {
+                    (date.clone(), None, "`date` is today: `updated` removed")
+                } else if comparison.is_less_than_or_equal_date(&last, updated) {
+                    // Values are fine, keep same (Unless `updated` was replaced above)
+                    (
+                        date.clone(),
+                        Some(updated.clone()),
+                        updated_fixup_rule
+                            .unwrap_or("Last commit is on or before `updated`: values kept"),
+                    )
                 } else {
-                    // `updated` is too old. Set `updated` to TODAY
-                    (date.clone(), Some(TODAY.clone()))
+                    // `updated` is too old. Set `updated` to TODAY (or the last commit)
+                    (
+                        date.clone(),
+                        Some(edit_time.clone()),
+                        set_to(
+                            "Last commit is after `updated`: `updated` set to today",
+                            "Last commit is after `updated`: `updated` set to the last commit",
+                        ),
+                    )
                 }
             }
         };
-        (new_date, new_updated)
+        (new_date, new_updated, rule)
     }
 
-    fn new(path: &'a Path, front_matter: String, content: String) -> Self {
+    fn new(
+        path: &'a Path,
+        format: FrontMatterFormat,
+        front_matter: String,
+        content: String,
+    ) -> Self {
         Self {
             is_changed: false,
             path,
+            format,
             front_matter,
             content,
+            original: String::new(),
+            rule_trace: None,
+            comparison: DateComparison::default(),
         }
     }
 
@@ -244,28 +668,41 @@ impl<'a> FileData<'a> {
 
     /// Build a FileData from a path
     ///
-    /// Splits the file data into front matter and content
+    /// Splits the file data into front matter and content (Supports both TOML and YAML front matter)
     /// Patterned on zola code https://github.com/c-git/zola/blob/3a73c9c5449f2deda0d287f9359927b0440a77af/components/content/src/front_matter/split.rs#L46
-    pub fn new_from_path(path: &Path) -> anyhow::Result<FileData> {
-        let content = fs::read_to_string(path).context("Failed to read file")?;
+    pub fn new_from_path(path: &Path) -> anyhow::Result<FileData<'_>> {
+        let content = fs::read_to_string(path).map_err(Error::io(path))?;
+        FileData::new_from_str(path, &content)
+    }
 
+    /// Build a FileData from the content of the file at `path` (See [`Self::new_from_path`])
+    pub fn new_from_str(path: &'a Path, content: &str) -> anyhow::Result<FileData<'a>> {
         // 2. extract the front matter and the content
-        let caps = if let Some(caps) = TOML_RE.captures(&content) {
-            caps
+        let (format, caps) = if let Some(caps) = TOML_RE.captures(content) {
+            (FrontMatterFormat::Toml, caps)
+        } else if let Some(caps) = YAML_RE.captures(content) {
+            (FrontMatterFormat::Yaml, caps)
         } else {
-            bail!("Failed to find front matter");
+            return Err(Error::MissingFrontMatter {
+                path: path.to_path_buf(),
+            }
+            .into());
         };
         // caps[0] is the full match
         // caps[1] => front matter
         // caps[2] => content
         let front_matter = caps.get(1).unwrap().as_str().to_string();
-        let content = caps.get(2).map_or("", |m| m.as_str()).to_string();
+        let body = caps.get(2).map_or("", |m| m.as_str()).to_string();
 
-        Ok(FileData::new(path, front_matter, content))
+        Ok(FileData {
+            original: content.to_string(),
+            ..FileData::new(path, format, front_matter, body)
+        })
     }
 }
 
 fn is_new_same_as_org(
+    comparison: &DateComparison,
     org_date: Option<&toml_edit::Item>,
     org_updated: Option<&toml_edit::Item>,
     new_date: &toml_edit::Item,
@@ -274,90 +711,52 @@ fn is_new_same_as_org(
     // Check if we've changed the starting values
     // NB: - date must change if it was None
     //     - This approach is slower due to loss of short circuit evaluation but I can read it, before it was...
-    let is_date_same = org_date.is_some() && is_equal_date(org_date.unwrap(), new_date);
+    let is_date_same = org_date.is_some() && comparison.is_equal_date(org_date.unwrap(), new_date);
     let did_update_start_and_end_none =
         org_updated.is_none() && org_updated.is_none() == new_updated.is_none();
     let did_updated_start_some_and_end_same_value = org_updated.is_some()
         && new_updated.is_some()
-        && is_equal_date(org_updated.unwrap(), new_updated.as_ref().unwrap());
+        && comparison.is_equal_date(org_updated.unwrap(), new_updated.as_ref().unwrap());
     let is_update_same = did_update_start_and_end_none || did_updated_start_some_and_end_same_value;
 
     is_date_same && is_update_same
 }
 
-/// Checks if both a and b are dates and if a < b
-fn is_less_than_date(a: &toml_edit::Item, b: &toml_edit::Item) -> bool {
-    match (a, b) {
-        (toml_edit::Item::Value(a), toml_edit::Item::Value(b)) => match (a, b) {
-            (toml_edit::Value::Datetime(a), toml_edit::Value::Datetime(b)) => {
-                match (a.value().date, b.value().date) {
-                    (Some(a), Some(b)) => match a.year.cmp(&b.year) {
-                        std::cmp::Ordering::Less => true,
-                        std::cmp::Ordering::Equal => match a.month.cmp(&b.month) {
-                            std::cmp::Ordering::Less => true,
-                            std::cmp::Ordering::Equal => a.day < b.day,
-                            std::cmp::Ordering::Greater => false,
-                        },
-                        std::cmp::Ordering::Greater => false,
-                    },
-                    _ => false,
-                }
-            }
-            _ => false,
-        },
-        _ => false,
-    }
+/// Checks if `item` is a date (with or without a time). A time on its own is not a date
+fn is_date(item: &toml_edit::Item) -> bool {
+    item.as_datetime().is_some_and(|dt| dt.date.is_some())
 }
 
-/// Check if both a and b are dates and if a <= b
-fn is_less_than_or_equal_date(a: &toml_edit::Item, b: &toml_edit::Item) -> bool {
-    is_less_than_date(a, b) || is_equal_date(a, b)
+fn item_from_datetime(d: toml_edit::Datetime) -> toml_edit::Item {
+    toml_edit::Item::Value(toml_edit::Value::Datetime(toml_edit::Formatted::new(d)))
 }
+
+#[cfg(test)]
 fn item_from_date(d: toml_edit::Date) -> toml_edit::Item {
-    toml_edit::Item::Value(toml_edit::Value::Datetime(toml_edit::Formatted::new(
-        toml_edit::Datetime {
-            date: Some(d),
-            time: None,
-            offset: None,
-        },
-    )))
+    item_from_datetime(d.into())
 }
 
-// Check if both a and b are dates and a == b
-fn is_equal_date(a: &toml_edit::Item, b: &toml_edit::Item) -> bool {
-    match (a, b) {
-        (toml_edit::Item::Value(a), toml_edit::Item::Value(b)) => match (a, b) {
-            (toml_edit::Value::Datetime(a), toml_edit::Value::Datetime(b)) => {
-                match (a.value().date, b.value().date) {
-                    (Some(a), Some(b)) => a.year == b.year && a.month == b.month && a.day == b.day,
-                    _ => false,
-                }
-            }
-            _ => false,
-        },
-        _ => false,
+/// The value of `item` as written (without surrounding whitespace or comments)
+fn item_to_display(item: &toml_edit::Item) -> String {
+    match item.as_value() {
+        Some(value) => value.clone().decorated("", "").to_string(),
+        None => item.to_string().trim().to_string(),
     }
 }
 
-/// Helper function to print dates in items. Panics if item is not a well formed date item object or None
-fn date_to_display(d: Option<&toml_edit::Item>) -> String {
-    if let Some(d) = d {
-        if let toml_edit::Item::Value(d) = d {
-            if let toml_edit::Value::Datetime(d) = d {
-                if let Some(d) = d.value().date {
-                    format!("{:0>4}-{:0>2}-{:0>2}", d.year, d.month, d.day)
-                } else {
-                    panic!("Expected Some")
-                }
-            } else {
-                panic!("Expected Datetime")
-            }
-        } else {
-            panic!("Expected Value")
-        }
-    } else {
-        "None".to_string()
-    }
+/// Helper function to print the day of dates in items (`None` if there is no item)
+fn date_to_display(d: Option<&toml_edit::Item>) -> anyhow::Result<String> {
+    let Some(d) = d else {
+        return Ok("None".to_string());
+    };
+    let date = d
+        .as_datetime()
+        .and_then(|dt| dt.date)
+        .with_context(|| format!("Expected a date but found: {}", item_to_display(d)))?;
+    Ok(format!(
+        "{:0>4}-{:0>2}-{:0>2}",
+        date.year, date.month, date.day
+    ))
 }
 
 #[cfg(test)]
@@ -365,9 +764,17 @@ fn date_to_display(d: Option<&toml_edit::Item>) -> String {
 mod test_helpers {
     use super::*;
 
+    static TODAY: Lazy<toml_edit::Item> = Lazy::new(|| {
+        item_from_date(toml_edit::Date {
+            year: 2024,
+            month: 6,
+            day: 15,
+        })
+    });
+
     #[test]
     fn test_is_equal_date() {
-        assert!(is_equal_date(&TODAY, &TODAY));
+        assert!(DateComparison::default().is_equal_date(&TODAY, &TODAY));
     }
 
     #[test]
@@ -377,9 +784,9 @@ mod test_helpers {
             month: 1,
             day: 1,
         });
-        assert!(is_less_than_date(&past, &TODAY));
-        assert!(!is_less_than_date(&TODAY, &past));
-        assert!(!is_less_than_date(&TODAY, &TODAY));
+        assert!(DateComparison::default().is_less_than_date(&past, &TODAY));
+        assert!(!DateComparison::default().is_less_than_date(&TODAY, &past));
+        assert!(!DateComparison::default().is_less_than_date(&TODAY, &TODAY));
     }
 
     #[test]
@@ -389,9 +796,9 @@ mod test_helpers {
             month: 1,
             day: 1,
         });
-        assert!(is_less_than_or_equal_date(&past, &TODAY));
-        assert!(!is_less_than_or_equal_date(&TODAY, &past));
-        assert!(is_less_than_or_equal_date(&TODAY, &TODAY));
+        assert!(DateComparison::default().is_less_than_or_equal_date(&past, &TODAY));
+        assert!(!DateComparison::default().is_less_than_or_equal_date(&TODAY, &past));
+        assert!(DateComparison::default().is_less_than_or_equal_date(&TODAY, &TODAY));
     }
 }
 
@@ -407,10 +814,8 @@ mod test_function {
     type DT = (u16, u8, u8); // Date Tuple
     type DTopt = Option<DT>; // Date Tuple Option
 
-    static TODAY_TUPLE: Lazy<DTopt> = Lazy::new(|| {
-        let now = chrono::Local::now();
-        Some((now.year() as _, now.month() as _, now.day() as _))
-    });
+    /// Fixed so that results do not depend on when the tests are run
+    const TODAY_TUPLE: &DTopt = &Some((2024, 6, 15));
 
     fn item_from_tuple_opt(value: DTopt) -> toml_edit::Item {
         if let Some(tuple) = value {
@@ -437,14 +842,14 @@ mod test_function {
             (None, None) => (),
             (None, Some(_)) | (Some(_), None) => panic!(
                 "{variable_name:?} actual does not match expected.\nactual: {}\nexpected: {}",
-                date_to_display(actual),
-                date_to_display(expected)
+                date_to_display(actual).unwrap(),
+                date_to_display(expected).unwrap()
             ),
             (Some(a), Some(b)) => assert!(
-                is_equal_date(a, b),
+                DateComparison::default().is_equal_date(a, b),
                 "{variable_name:?} actual does not match expected.\nactual: {}\nexpected: {}",
-                date_to_display(actual),
-                date_to_display(expected)
+                date_to_display(actual).unwrap(),
+                date_to_display(expected).unwrap()
             ),
         }
     }
@@ -508,7 +913,12 @@ mod test_function {
     ) {
         println!("Test Name: {test_name:?}");
         let path = PathBuf::new();
-        let mock = FileData::new(&path, Default::default(), Default::default());
+        let mock = FileData::new(
+            &path,
+            FrontMatterFormat::Toml,
+            Default::default(),
+            Default::default(),
+        );
 
         // Set org_date
         let item = item_from_tuple_opt(date);
@@ -532,7 +942,9 @@ mod test_function {
         // Set expected_is_changed
         let expected_is_changed = match (org_date, expected_date, org_updated, expected_updated) {
             (None, None, None, None) => false,
-            (Some(a), Some(b), None, None) | (None, None, Some(a), Some(b)) => !is_equal_date(a, b),
+            (Some(a), Some(b), None, None) | (None, None, Some(a), Some(b)) => {
+                !DateComparison::default().is_equal_date(a, b)
+            }
             (None, None, None, Some(_))
             | (None, None, Some(_), None)
             | (None, Some(_), None, None)
@@ -545,14 +957,28 @@ mod test_function {
             | (Some(_), None, Some(_), Some(_))
             | (Some(_), Some(_), None, Some(_))
             | (Some(_), Some(_), Some(_), None) => true, // Mismatched pairs (Some with None)
-            (Some(a), Some(b), Some(c), Some(d)) => !is_equal_date(a, b) || !is_equal_date(c, d),
+            (Some(a), Some(b), Some(c), Some(d)) => {
+                !DateComparison::default().is_equal_date(a, b)
+                    || !DateComparison::default().is_equal_date(c, d)
+            }
         };
 
-        let (actual_date, actual_updated) =
-            mock.calculate_new_date_and_updated(org_date, org_updated, last_edit_date);
+        let (actual_date, actual_updated, _) = mock.calculate_new_date_and_updated(
+            org_date,
+            org_updated,
+            last_edit_date.map(Into::into),
+            None,
+            date_from_tuple(TODAY_TUPLE.unwrap()).into(),
+            &mut vec![],
+        );
 
-        let actual_is_changed =
-            !is_new_same_as_org(org_date, org_updated, &actual_date, &actual_updated);
+        let actual_is_changed = !is_new_same_as_org(
+            &DateComparison::default(),
+            org_date,
+            org_updated,
+            &actual_date,
+            &actual_updated,
+        );
 
         assert_same(Some(&actual_date), expected_date, "date");
         assert_same(actual_updated.as_ref(), expected_updated, "updated");
@@ -561,4 +987,362 @@ mod test_function {
             "is_changed doesn't match expectation"
         );
     }
+
+    #[rstest]
+    #[case(PAST2,        PAST1,        None,         None,         PAST1,        *TODAY_TUPLE, "01")]
+    #[case(PAST2,        PAST2,        None,         None,         PAST2,        None,  /* */  "02")]
+    #[case(PAST2,        PAST1,        PAST1,        None,         PAST1,        *TODAY_TUPLE, "03")]
+    #[case(PAST2,        PAST1,        None,         PAST3,        PAST1,        PAST3, /* */  "04")]
+    #[case(PAST2,        PAST1,        None,         FUTURE,       PAST1,        *TODAY_TUPLE, "05")]
+    #[case(PAST2,        None,         None,         None,         PAST2,        *TODAY_TUPLE, "06")]
+    #[case(PAST3,        PAST1,        PAST2,        None,         PAST2,        *TODAY_TUPLE, "07")]
+    #[case(*TODAY_TUPLE, *TODAY_TUPLE, None,         None,         *TODAY_TUPLE, None,         "08")]
+    #[case(PAST2,        FUTURE,       None,         None,         PAST2,        *TODAY_TUPLE, "09")]
+    fn first_commit_date_case(
+        #[case] last: DTopt,
+        #[case] first: DTopt,
+        #[case] date: DTopt,
+        #[case] updated: DTopt,
+        #[case] expected_date: DTopt,
+        #[case] expected_updated: DTopt,
+        #[case] test_name: &str,
+    ) {
+        println!("Test Name: {test_name:?}");
+        let path = PathBuf::new();
+        let mock = FileData::new(
+            &path,
+            FrontMatterFormat::Toml,
+            Default::default(),
+            Default::default(),
+        );
+        let date = item_from_tuple_opt(date);
+        let org_date = date.is_datetime().then_some(&date);
+        let updated = item_from_tuple_opt(updated);
+        let org_updated = updated.is_datetime().then_some(&updated);
+        let expected_date = item_from_tuple_opt(expected_date);
+        let expected_updated = item_from_tuple_opt(expected_updated);
+
+        let (actual_date, actual_updated, _) = mock.calculate_new_date_and_updated(
+            org_date,
+            org_updated,
+            last.map(date_from_tuple).map(Into::into),
+            first.map(date_from_tuple).map(Into::into),
+            date_from_tuple(TODAY_TUPLE.unwrap()).into(),
+            &mut vec![],
+        );
+
+        assert_same(Some(&actual_date), Some(&expected_date), "date");
+        assert_same(
+            actual_updated.as_ref(),
+            expected_updated.is_datetime().then_some(&expected_updated),
+            "updated",
+        );
+    }
+
+    #[rstest]
+    #[case(
+        "+++\ntitle = \"Hi\" # comment\ndate = 2001-01-01\n+++\n\nBody\n",
+        "+++\ntitle = \"Hi\" # comment\ndate = 2001-01-01\nupdated = {TODAY}\n+++\n\nBody\n"
+    )]
+    #[case(
+        "---\ntitle: Hi # comment\ndate: 2001-01-01\ntags: [a]\n---\n\nBody\n",
+        "---\ntitle: Hi # comment\ndate: 2001-01-01\nupdated: {TODAY}\ntags: [a]\n---\n\nBody\n"
+    )]
+    #[case(
+        "---\ntitle: Hi\n---\n",
+        "---\ntitle: Hi\ndate: 2002-01-01\nupdated: {TODAY}\n---\n"
+    )]
+    fn front_matter_formats(#[case] input: &str, #[case] expected: &str) {
+        let path = PathBuf::new();
+        let today = item_from_tuple_opt(*TODAY_TUPLE);
+        let expected = expected.replace("{TODAY}", &date_to_display(Some(&today)).unwrap());
+        let mut data = FileData::new_from_str(&path, input).unwrap();
+        data.update_front_matter(
+            PAST2.map(date_from_tuple).map(Into::into),
+            None,
+            date_from_tuple(TODAY_TUPLE.unwrap()).into(),
+        )
+        .unwrap();
+        assert!(data.is_changed());
+        assert_eq!(data.to_file_string(), expected);
+    }
+
+    #[rstest]
+    #[case(
+        "2001-01-01T23:00:00+02:00",
+        "+++\ndate = 2001-01-01T10:00:00+02:00\n+++\n"
+    )]
+    #[case(
+        "2002-01-01T08:00:00+02:00",
+        "+++\ndate = 2001-01-01T10:00:00+02:00\nupdated = 2024-06-15T12:30:00+02:00\n+++\n"
+    )]
+    fn full_datetimes(#[case] last_edit_date: &str, #[case] expected: &str) {
+        let path = PathBuf::new();
+        let input = "+++\ndate = 2001-01-01T10:00:00+02:00\n+++\n";
+        let mut data = FileData::new_from_str(&path, input).unwrap();
+        data.update_front_matter(
+            Some(last_edit_date.parse().unwrap()),
+            None,
+            "2024-06-15T12:30:00+02:00".parse().unwrap(),
+        )
+        .unwrap();
+        assert_eq!(data.is_changed(), input != expected);
+        assert_eq!(data.to_file_string(), expected);
+    }
+
+    const MIXED_LAST_EDIT: &str = "2023-05-01T23:30:00-05:00"; // 2023-05-02T04:30:00Z
+
+    #[rstest]
+    #[case(
+        Precision::OffsetDatetime,
+        "UTC",
+        "+++\ndate = 2023-05-02T01:00:00Z\n+++\n",
+        "+++\ndate = 2023-05-02T01:00:00Z\nupdated = 2023-05-01T23:30:00-05:00\n+++\n"
+    )]
+    #[case(
+        Precision::OffsetDatetime,
+        "UTC",
+        "+++\ndate = 2023-05-02T05:00:00Z\n+++\n",
+        "+++\ndate = 2023-05-02T05:00:00Z\n+++\n"
+    )]
+    #[case(
+        Precision::Date,
+        "UTC",
+        "+++\ndate = 2023-05-02T01:00:00Z\n+++\n",
+        "+++\ndate = 2023-05-02T01:00:00Z\n+++\n"
+    )]
+    #[case(
+        Precision::Date,
+        "+10:00",
+        "+++\ndate = 2023-05-01T12:00:00Z\n+++\n",
+        "+++\ndate = 2023-05-01T12:00:00Z\nupdated = 2023-06-01T12:00:00Z\n+++\n"
+    )]
+    #[case(
+        Precision::LocalDatetime,
+        "UTC",
+        "+++\ndate = 2023-05-02T03:00:00\n+++\n",
+        "+++\ndate = 2023-05-02T03:00:00\nupdated = 2023-05-01T23:30:00-05:00\n+++\n"
+    )]
+    #[case(
+        Precision::LocalDatetime,
+        "-05:00",
+        "+++\ndate = 2023-05-02T03:00:00\n+++\n",
+        "+++\ndate = 2023-05-02T03:00:00\n+++\n"
+    )]
+    #[case(
+        Precision::OffsetDatetime,
+        "UTC",
+        "+++\ndate = 2023-05-02T01:00:00Z\nupdated = 2023-05-02T03:00:00+02:00\n+++\n",
+        "+++\ndate = 2023-05-02T01:00:00Z\nupdated = 2023-05-01T23:30:00-05:00\n+++\n"
+    )]
+    fn mixed_offsets(
+        #[case] precision: Precision,
+        #[case] time_zone: &str,
+        #[case] input: &str,
+        #[case] expected: &str,
+    ) {
+        let path = PathBuf::new();
+        let mut data = FileData::new_from_str(&path, input).unwrap();
+        data.set_comparison(DateComparison {
+            precision,
+            time_zone: Some(time_zone.parse().unwrap()),
+        });
+        data.update_front_matter(
+            Some(MIXED_LAST_EDIT.parse().unwrap()),
+            None,
+            "2023-06-01T12:00:00Z".parse().unwrap(),
+        )
+        .unwrap();
+        assert_eq!(data.is_changed(), input != expected);
+        assert_eq!(data.to_file_string(), expected);
+    }
+
+    #[test]
+    fn time_only_value() {
+        let path = PathBuf::new();
+        let input = "+++\ndate = 10:00:00\n+++\n";
+        let mut data = FileData::new_from_str(&path, input).unwrap();
+        let item = toml_edit::value("10:00:00".parse::<toml_edit::Datetime>().unwrap());
+        assert!(date_to_display(Some(&item)).is_err());
+        data.update_front_matter(
+            Some(MIXED_LAST_EDIT.parse().unwrap()),
+            None,
+            "2023-06-01T12:00:00Z".parse().unwrap(),
+        )
+        .unwrap();
+        assert_eq!(
+            data.to_file_string(),
+            "+++\ndate = 2023-05-01T23:30:00-05:00\nupdated = 2023-06-01T12:00:00Z\n+++\n"
+        );
+    }
+
+    #[rstest]
+    #[case(
+        "---\ndate: 2001-01-01 10:00:00 +02:00\n---\n",
+        "---\ndate: 2001-01-01 10:00:00 +02:00\nupdated: {TODAY}\n---\n"
+    )]
+    #[case(
+        "---\ndate: 2001-01-01t10:00:00z\n---\n",
+        "---\ndate: 2001-01-01t10:00:00z\nupdated: {TODAY}\n---\n"
+    )]
+    fn yaml_timestamps_kept(#[case] input: &str, #[case] expected: &str) {
+        let path = PathBuf::new();
+        let today = item_from_tuple_opt(*TODAY_TUPLE);
+        let expected = expected.replace("{TODAY}", &date_to_display(Some(&today)).unwrap());
+        let mut data = FileData::new_from_str(&path, input).unwrap();
+        assert!(data.has_date().unwrap());
+        data.update_front_matter(
+            PAST2.map(date_from_tuple).map(Into::into),
+            None,
+            date_from_tuple(TODAY_TUPLE.unwrap()).into(),
+        )
+        .unwrap();
+        assert_eq!(data.to_file_string(), expected);
+    }
+
+    #[rstest]
+    #[case("---\ntitle Hi\n---\n")]
+    #[case("---\ndate: 2001-02-30\n---\n")]
+    fn invalid_yaml_is_an_error(#[case] input: &str) {
+        let path = PathBuf::new();
+        let mut data = FileData::new_from_str(&path, input).unwrap();
+        let error = data
+            .update_front_matter(None, None, date_from_tuple(TODAY_TUPLE.unwrap()).into())
+            .unwrap_err();
+        assert_eq!(Error::kind_of(&error), crate::ErrorKind::InvalidYaml);
+        assert!(!data.is_changed());
+    }
+
+    #[rstest]
+    #[case("+++\ndraft = true\n+++\n", true)]
+    #[case("+++\ndraft = false\n+++\n", false)]
+    #[case("+++\ntitle = \"draft\"\n+++\n", false)]
+    #[case("---\ndraft: true\n---\n", true)]
+    fn draft_pages(#[case] input: &str, #[case] expected: bool) {
+        let path = PathBuf::new();
+        let data = FileData::new_from_str(&path, input).unwrap();
+        assert_eq!(data.is_draft().unwrap(), expected);
+    }
+
+    #[rstest]
+    #[case(
+        "+++\ndraft = true\ndate = 2024-01-01\nupdated = 2024-02-01\n+++\n",
+        "+++\ndraft = true\n+++\n"
+    )]
+    #[case("---\ndate: 2024-01-01\ndraft: true\n---\n", "---\ndraft: true\n---\n")]
+    #[case("+++\ndraft = true\n+++\n", "+++\ndraft = true\n+++\n")]
+    fn clear_dates(#[case] input: &str, #[case] expected: &str) {
+        let path = PathBuf::new();
+        let mut data = FileData::new_from_str(&path, input).unwrap();
+        data.clear_dates().unwrap();
+        assert_eq!(data.is_changed(), input != expected);
+        assert_eq!(data.to_file_string(), expected);
+    }
+
+    #[rstest]
+    #[case("+++\ndate = 2023-04-01\n+++\n", "+++\ndate = 2023-04-01\n+++\n")]
+    #[case(
+        "+++\ndate = 2023-04-01T10:00:00\n+++\n",
+        "+++\ndate = 2023-04-01T10:00:00\n+++\n"
+    )]
+    #[case("+++\ndate = 2023-05-06\n+++\n", "+++\ndate = 2023-04-01\n+++\n")]
+    #[case(
+        "+++\ntitle = \"a\"\n+++\n",
+        "+++\ntitle = \"a\"\ndate = 2023-04-01\n+++\n"
+    )]
+    #[case("---\ndate: 2023-05-06\n---\n", "---\ndate: 2023-04-01\n---\n")]
+    fn set_date(#[case] input: &str, #[case] expected: &str) {
+        let path = PathBuf::new();
+        let mut data = FileData::new_from_str(&path, input).unwrap();
+        data.set_date("2023-04-01".parse().unwrap()).unwrap();
+        assert_eq!(data.is_changed(), input != expected);
+        assert_eq!(data.to_file_string(), expected);
+    }
+
+    #[test]
+    fn unified_diff() {
+        let path = PathBuf::from("blog/a.md");
+        let input = "+++\ntitle = \"A\"\ndate = 2024-01-01\n+++\n\nSome content\n";
+        let mut data = FileData::new_from_str(&path, input).unwrap();
+        assert_eq!(data.unified_diff(&path), "");
+        data.update_front_matter(
+            Some("2024-02-01".parse().unwrap()),
+            None,
+            "2024-06-15".parse().unwrap(),
+        )
+        .unwrap();
+        let expected = "\
+--- a/blog/a.md
++++ b/blog/a.md
+@@ -1,6 +1,7 @@
+ +++
+ title = \"A\"
+ date = 2024-01-01
++updated = 2024-06-15
+ +++
+ 
+ Some content
+";
+        assert_eq!(data.unified_diff(&path), expected);
+    }
+
+    #[test]
+    fn rule_trace() {
+        let path = PathBuf::new();
+        let input = "+++\ndate = 2030-01-01\nupdated = 2024-01-01 # Comment\n+++\n";
+        let mut data = FileData::new_from_str(&path, input).unwrap();
+        assert_eq!(data.rule_trace(), None);
+        data.update_front_matter(
+            Some("2024-02-01".parse().unwrap()),
+            None,
+            "2024-06-15".parse().unwrap(),
+        )
+        .unwrap();
+        let expected = RuleTrace {
+            org_date: Some("2030-01-01".to_string()),
+            org_updated: Some("2024-01-01".to_string()),
+            last_edit_date: Some("2024-02-01".to_string()),
+            fallback_date: None,
+            today: "2024-06-15".to_string(),
+            fixups: vec![
+                "`updated` is before `date` but this should never happen. `updated` being ignored"
+                    .to_string(),
+                "date is set in the future. Date is being ignored".to_string(),
+            ],
+            rule: "No `date` and last commit is before today: `date` set to the last commit date and `updated` set to today",
+            new_date: "2024-02-01".to_string(),
+            new_updated: Some("2024-06-15".to_string()),
+        };
+        assert_eq!(data.rule_trace(), Some(&expected));
+    }
+
+    #[rstest]
+    #[case(
+        "updated = \"soon\"",
+        "`updated` is not a date: `updated` set to today"
+    )]
+    #[case(
+        "updated = 2019-01-01",
+        "`updated` is before `date`: `updated` set to today"
+    )]
+    #[case(
+        "updated = 2030-01-01",
+        "`updated` is in the future: `updated` set to today"
+    )]
+    #[case(
+        "updated = 2024-03-01",
+        "Last commit is on or before `updated`: values kept"
+    )]
+    fn rule_of_updated_fixup(#[case] updated_line: &str, #[case] expected_rule: &str) {
+        let path = PathBuf::new();
+        let input = format!("+++\ndate = 2020-01-01\n{updated_line}\n+++\n");
+        let mut data = FileData::new_from_str(&path, &input).unwrap();
+        data.update_front_matter(
+            Some("2024-02-01".parse().unwrap()),
+            None,
+            "2024-06-15".parse().unwrap(),
+        )
+        .unwrap();
+        assert_eq!(data.rule_trace().unwrap().rule, expected_rule);
+    }
 }
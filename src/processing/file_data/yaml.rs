@@ -0,0 +1,431 @@
+//! Minimal line based editing of YAML front matter
+//!
+//! Only top level scalar keys are supported as that is all that is needed to read and set dates.
+//! Editing is done on the lines directly so that everything else (key order, comments, formatting) is left untouched.
+//! Top level lines that could not be understood are rejected instead of being edited around.
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+/// Matches a top level key (no indentation) and captures the key and everything after the colon
+static KEY_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^(?P<key>[A-Za-z_][A-Za-z0-9_-]*)[ \t]*:(?P<rest>.*)$").unwrap());
+
+/// Matches a top level key that is quoted (Allowed but never one of the keys edited)
+static QUOTED_KEY_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r#"^(?:"[^"]*"|'[^']*')[ \t]*:(?:[ \t]|$)"#).unwrap());
+
+/// Matches the timestamps YAML accepts (Which TOML does not all accept as is, eg. a space before the offset)
+static TIMESTAMP_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"^(?P<year>\d{4})-(?P<month>\d{1,2})-(?P<day>\d{1,2})(?:(?:[Tt]|[ \t]+)(?P<hour>\d{1,2}):(?P<minute>\d{2}):(?P<second>\d{2})(?P<fraction>\.\d*)?(?:[ \t]*(?P<offset>[Zz]|(?P<sign>[-+])(?P<offset_hour>\d{1,2})(?::?(?P<offset_minute>\d{2}))?))?)?$",
+    )
+    .unwrap()
+});
+
+/// Matches values that start like a date (Used to report dates that could not be read instead of treating them as text)
+static DATE_LIKE_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"^\d{4}-\d{1,2}-\d{1,2}").unwrap());
+
+/// The keys that must hold a date if they look like one
+const DATE_KEYS: [&str; 2] = ["date", "updated"];
+
+#[derive(Debug)]
+pub struct YamlFrontMatter {
+    lines: Vec<String>,
+    is_crlf: bool,
+}
+
+/// The parts of the text after the colon of a top level key
+#[derive(Debug, PartialEq, Eq)]
+struct ValueParts<'a> {
+    /// Whitespace between the colon and the value
+    leading: &'a str,
+    /// The value without quotes
+    value: &'a str,
+    /// The quote character used if any
+    quote: Option<char>,
+    /// Everything after the value (trailing whitespace and comment)
+    trailing: &'a str,
+}
+
+impl YamlFrontMatter {
+    /// Splits `s` into lines and checks that the top level can be edited safely
+    ///
+    /// Returns a description of the first problem found (eg. a line that is not a key, a duplicate key or an invalid date).
+    pub fn parse(s: &str) -> Result<Self, String> {
+        let result = Self {
+            lines: s.split('\n').map(|line| line.to_string()).collect(),
+            is_crlf: s.contains("\r\n"),
+        };
+        let mut keys = std::collections::HashSet::new();
+        // Line 0 is the rest of the line of the opening delimiter so the index is the line number in the front matter
+        for (line_number, line) in result.lines.iter().enumerate() {
+            let line = line.strip_suffix('\r').unwrap_or(line);
+            let trimmed = line.trim_start();
+            let is_other = trimmed.is_empty()
+                || trimmed.starts_with('#')
+                || trimmed.len() != line.len() // Nested (or continues the value above)
+                || line == "-"
+                || line.starts_with("- ")
+                || QUOTED_KEY_RE.is_match(line);
+            if is_other {
+                continue;
+            }
+            let Some(caps) = KEY_RE.captures(line) else {
+                return Err(format!(
+                    "line {line_number} is not a top level key: {line:?}"
+                ));
+            };
+            let key = caps.name("key").expect("required group").as_str();
+            if !keys.insert(key) {
+                return Err(format!("line {line_number} repeats the key `{key}`"));
+            }
+            let value = split_value(&caps["rest"]).value;
+            if DATE_KEYS.contains(&key)
+                && DATE_LIKE_RE.is_match(value)
+                && datetime_from_str(value).is_none()
+            {
+                return Err(format!(
+                    "line {line_number} has an invalid date for `{key}`: {value:?}"
+                ));
+            }
+        }
+        Ok(result)
+    }
+
+    /// Returns the value of the top level key if found
+    ///
+    /// Values that are valid YAML timestamps or booleans are converted to the equivalent TOML value otherwise they are returned as strings.
+    pub fn get(&self, key: &str) -> Option<toml_edit::Item> {
+        let index = self.find_key(key)?;
+        let parts = split_value(self.rest_of_line(index)?);
+        let value = if parts.quote.is_none() {
+            match parts.value {
+                "true" => toml_edit::Value::from(true),
+                "false" => toml_edit::Value::from(false),
+                s => toml_value_from_str(s),
+            }
+        } else {
+            toml_value_from_str(parts.value)
+        };
+        Some(toml_edit::Item::Value(value))
+    }
+
+    /// Sets the value of the top level key to `item`
+    ///
+    /// If the key already exists only the value is replaced (quoting and comments are preserved) otherwise a new line is added.
+    /// Nothing is changed if the key already has the same value.
+    pub fn set(&mut self, key: &str, item: &toml_edit::Item) {
+        let value = item_to_yaml_value(item);
+        // Keep the value as written if it is the same (eg. a timestamp in a form that differs from TOML)
+        if self
+            .get(key)
+            .is_some_and(|current| item_to_yaml_value(&current) == value)
+        {
+            return;
+        }
+        if let Some(index) = self.find_key(key) {
+            let line = &self.lines[index];
+            let (line, cr) = match line.strip_suffix('\r') {
+                Some(line) => (line, "\r"),
+                None => (line.as_str(), ""),
+            };
+            let rest = KEY_RE
+                .captures(line)
+                .and_then(|caps| caps.name("rest"))
+                .expect("found key so rest must exist");
+            let parts = split_value(rest.as_str());
+            let leading = if parts.leading.is_empty() {
+                " "
+            } else {
+                parts.leading
+            };
+            let quote = parts.quote.map(String::from).unwrap_or_default();
+            let new_line = format!(
+                "{}{leading}{quote}{value}{quote}{}{cr}",
+                &line[..rest.start()],
+                parts.trailing
+            );
+            self.lines[index] = new_line;
+        } else {
+            let mut new_line = format!("{key}: {value}");
+            if self.is_crlf {
+                new_line.push('\r');
+            }
+            // Keep `updated` next to `date` if possible
+            let index = if key == "updated" {
+                self.find_key("date").map(|i| i + 1)
+            } else {
+                None
+            }
+            .unwrap_or_else(|| self.insert_at_end_index());
+            self.lines.insert(index, new_line);
+        }
+    }
+
+    /// Removes the line with the top level key if it exists
+    pub fn remove(&mut self, key: &str) {
+        if let Some(index) = self.find_key(key) {
+            self.lines.remove(index);
+        }
+    }
+
+    fn find_key(&self, key: &str) -> Option<usize> {
+        self.lines.iter().position(|line| {
+            KEY_RE
+                .captures(line)
+                .is_some_and(|caps| &caps["key"] == key)
+        })
+    }
+
+    /// Returns the text after the colon for the line at `index` excluding any trailing `\r`
+    fn rest_of_line(&self, index: usize) -> Option<&str> {
+        let line = self.lines[index]
+            .strip_suffix('\r')
+            .unwrap_or(&self.lines[index]);
+        KEY_RE
+            .captures(line)
+            .and_then(|caps| caps.name("rest"))
+            .map(|m| m.as_str())
+    }
+
+    /// New keys are added after the last line with content (The last line is normally empty because the text ends with a newline)
+    fn insert_at_end_index(&self) -> usize {
+        match self.lines.last() {
+            Some(last) if last.trim().is_empty() && self.lines.len() > 1 => self.lines.len() - 1,
+            _ => self.lines.len(),
+        }
+    }
+}
+
+impl std::fmt::Display for YamlFrontMatter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.lines.join("\n"))
+    }
+}
+
+fn split_value(rest: &str) -> ValueParts<'_> {
+    let trimmed = rest.trim_start();
+    let leading = &rest[..rest.len() - trimmed.len()];
+    let first = trimmed.chars().next();
+    if let Some(quote @ ('"' | '\'')) = first {
+        if let Some(end) = trimmed[1..].find(quote) {
+            return ValueParts {
+                leading,
+                value: &trimmed[1..=end],
+                quote: Some(quote),
+                trailing: &trimmed[end + 2..],
+            };
+        }
+    }
+    // A comment in YAML must be preceded by whitespace
+    let value_end = trimmed
+        .find(" #")
+        .or_else(|| trimmed.find("\t#"))
+        .unwrap_or(trimmed.len());
+    let value = trimmed[..value_end].trim_end();
+    ValueParts {
+        leading,
+        value,
+        quote: None,
+        trailing: &trimmed[value.len()..],
+    }
+}
+
+fn toml_value_from_str(s: &str) -> toml_edit::Value {
+    match datetime_from_str(s) {
+        Some(dt) => toml_edit::Value::from(dt),
+        None => toml_edit::Value::from(s),
+    }
+}
+
+/// Reads a YAML timestamp by rewriting it in the form TOML uses (eg. `2023-5-1 10:00:00 +2` becomes `2023-05-01T10:00:00+02:00`)
+fn datetime_from_str(s: &str) -> Option<toml_edit::Datetime> {
+    let caps = TIMESTAMP_RE.captures(s)?;
+    let number = |name: &str| caps[name].parse::<u32>().ok();
+    let mut result = format!(
+        "{}-{:02}-{:02}",
+        &caps["year"],
+        number("month")?,
+        number("day")?
+    );
+    if caps.name("hour").is_some() {
+        result.push_str(&format!(
+            "T{:02}:{}:{}",
+            number("hour")?,
+            &caps["minute"],
+            &caps["second"]
+        ));
+        // TOML needs at least one digit after the point
+        if let Some(fraction) = caps.name("fraction").filter(|m| m.len() > 1) {
+            result.push_str(fraction.as_str());
+        }
+        if let Some(sign) = caps.name("sign") {
+            let minute = caps
+                .name("offset_minute")
+                .map_or(Some(0), |m| m.as_str().parse::<u32>().ok())?;
+            result.push_str(&format!(
+                "{}{:02}:{minute:02}",
+                sign.as_str(),
+                number("offset_hour")?
+            ));
+        } else if caps.name("offset").is_some() {
+            result.push('Z');
+        }
+    }
+    result.parse().ok()
+}
+
+/// Converts an item into the text to be used for the value in YAML
+fn item_to_yaml_value(item: &toml_edit::Item) -> String {
+    match item {
+        toml_edit::Item::Value(toml_edit::Value::Datetime(dt)) => dt.value().to_string(),
+        toml_edit::Item::Value(toml_edit::Value::String(s)) => s.value().to_string(),
+        _ => item.to_string().trim().to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    fn date_item(s: &str) -> toml_edit::Item {
+        toml_edit::Item::Value(toml_edit::Value::from(
+            s.parse::<toml_edit::Datetime>().unwrap(),
+        ))
+    }
+
+    #[rstest]
+    #[case("date: 2023-04-01", " ", "2023-04-01", None, "")]
+    #[case(
+        "date:   2023-04-01  # comment",
+        "   ",
+        "2023-04-01",
+        None,
+        "  # comment"
+    )]
+    #[case(
+        "date: \"2023-04-01\" # comment",
+        " ",
+        "2023-04-01",
+        Some('"'),
+        " # comment"
+    )]
+    #[case("date: '2023-04-01'", " ", "2023-04-01", Some('\''), "")]
+    #[case("date:", "", "", None, "")]
+    fn split(
+        #[case] line: &str,
+        #[case] leading: &str,
+        #[case] value: &str,
+        #[case] quote: Option<char>,
+        #[case] trailing: &str,
+    ) {
+        let rest = &line["date:".len()..];
+        let actual = split_value(rest);
+        assert_eq!(
+            actual,
+            ValueParts {
+                leading,
+                value,
+                quote,
+                trailing
+            }
+        );
+    }
+
+    #[test]
+    fn get_values() {
+        let fm = YamlFrontMatter::parse(
+            "\ntitle: \"A: title\"\ndate: 2023-04-01 # publish\ndraft: true\nextra:\n  date: 1999-01-01\n",
+        ).unwrap();
+        assert_eq!(
+            fm.get("date").unwrap().to_string(),
+            date_item("2023-04-01").to_string()
+        );
+        assert_eq!(fm.get("draft").unwrap().as_bool(), Some(true));
+        assert_eq!(fm.get("title").unwrap().as_str(), Some("A: title"));
+        assert!(fm.get("updated").is_none());
+    }
+
+    #[test]
+    fn set_preserves_everything_else() {
+        let mut fm = YamlFrontMatter::parse(
+            "\n# leading comment\ntitle: Hello\ndate: \"2023-04-01\" # publish\ntags: [a, b]\n",
+        )
+        .unwrap();
+        fm.set("date", &date_item("2023-05-02"));
+        fm.set("updated", &date_item("2023-06-03"));
+        assert_eq!(
+            fm.to_string(),
+            "\n# leading comment\ntitle: Hello\ndate: \"2023-05-02\" # publish\nupdated: 2023-06-03\ntags: [a, b]\n"
+        );
+        fm.remove("updated");
+        assert_eq!(
+            fm.to_string(),
+            "\n# leading comment\ntitle: Hello\ndate: \"2023-05-02\" # publish\ntags: [a, b]\n"
+        );
+    }
+
+    #[test]
+    fn set_new_key_at_end() {
+        let mut fm = YamlFrontMatter::parse("\r\ntitle: Hello\r\nupdated: 2023-01-01\r\n").unwrap();
+        fm.set("date", &date_item("2023-05-02"));
+        fm.set("updated", &date_item("2023-06-03"));
+        assert_eq!(
+            fm.to_string(),
+            "\r\ntitle: Hello\r\nupdated: 2023-06-03\r\ndate: 2023-05-02\r\n"
+        );
+    }
+
+    #[test]
+    fn nested_keys_ignored() {
+        let mut fm = YamlFrontMatter::parse("\nextra:\n  date: 1999-01-01\n").unwrap();
+        assert!(fm.get("date").is_none());
+        fm.set("date", &date_item("2023-05-02"));
+        assert_eq!(
+            fm.to_string(),
+            "\nextra:\n  date: 1999-01-01\ndate: 2023-05-02\n"
+        );
+    }
+
+    #[rstest]
+    #[case("2023-04-01", "2023-04-01")]
+    #[case("2023-4-1", "2023-04-01")]
+    #[case("2023-05-01T10:00:00Z", "2023-05-01T10:00:00Z")]
+    #[case("2023-05-01t10:00:00z", "2023-05-01T10:00:00Z")]
+    #[case("2023-05-01 10:00:00 +02:00", "2023-05-01T10:00:00+02:00")]
+    #[case("2023-05-01 10:00:00", "2023-05-01T10:00:00")]
+    #[case("2023-05-01 1:00:00.5 -5", "2023-05-01T01:00:00.5-05:00")]
+    #[case("2023-05-01T10:00:00.+0530", "2023-05-01T10:00:00+05:30")]
+    fn yaml_timestamps(#[case] yaml: &str, #[case] toml: &str) {
+        assert_eq!(datetime_from_str(yaml), Some(toml.parse().unwrap()));
+        let fm = YamlFrontMatter::parse(&format!("\ndate: {yaml}\n")).unwrap();
+        assert_eq!(
+            fm.get("date").unwrap().to_string(),
+            date_item(toml).to_string()
+        );
+    }
+
+    #[rstest]
+    #[case("\ntitle Hello\n", "line 1 is not a top level key")]
+    #[case(
+        "\ndate: 2023-01-01\ndate: 2023-02-01\n",
+        "line 2 repeats the key `date`"
+    )]
+    #[case("\ndate: 2023-13-45\n", "line 1 has an invalid date for `date`")]
+    #[case(
+        "\nupdated: \"2023-05-01 25:00:00\"\n",
+        "line 1 has an invalid date for `updated`"
+    )]
+    fn invalid_front_matter(#[case] input: &str, #[case] expected: &str) {
+        let actual = YamlFrontMatter::parse(input).unwrap_err();
+        assert!(actual.starts_with(expected), "{actual}");
+    }
+
+    #[test]
+    fn valid_front_matter() {
+        let input = "\n# comment\ntitle: Hi\ntags:\n- a\n- b\nextra:\n  date: not a date\n\"quoted key\": 1\nsummary: 2023-01-01 recap\n";
+        assert!(YamlFrontMatter::parse(input).is_ok());
+    }
+}
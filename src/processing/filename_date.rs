@@ -0,0 +1,72 @@
+//! Reads the date zola takes from the start of a page's file name (or the folder name of a page bundle)
+//!
+//! Zola accepts names like `2023-04-01-my-post.md` and `2023-04-01_my-post/index.md`.
+//! The date can also be a datetime with an offset like `2023-04-01T10:00:00Z-my-post.md` or `2023-04-01T10:00:00+02:00_my-post.md`.
+
+use std::path::Path;
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+use crate::site::is_bundle_file;
+
+/// Same as the pattern zola uses (The time is optional but must have seconds and an offset if present)
+static FILENAME_DATE_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"^(?P<datetime>(?P<year>\d{4})-(?P<month>\d{2})-(?P<day>\d{2})(?:T\d{2}:\d{2}:\d{2}(?:\.\d+)?(?:Z|[+-]\d{2}:\d{2}))?)\s?[-_]",
+    )
+    .unwrap()
+});
+
+/// Returns the date at the start of the name of the page at `path` if it has one
+pub fn filename_date(path: &Path) -> Option<toml_edit::Datetime> {
+    let name = if is_bundle_file(path) {
+        // Page bundle, the date is on the folder
+        path.parent()?.file_name()?.to_str()?
+    } else {
+        path.file_name()?.to_str()?
+    };
+    let caps = FILENAME_DATE_RE.captures(name)?;
+    let (year, month, day) = (
+        caps["year"].parse().ok()?,
+        caps["month"].parse().ok()?,
+        caps["day"].parse().ok()?,
+    );
+    // Reject values like `2023-13-45` that only look like dates
+    chrono::NaiveDate::from_ymd_opt(year, month, day)?;
+    // Also rejects times like `25:00:00`
+    caps["datetime"].parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case("blog/2023-04-01-my-post.md", Some("2023-04-01"))]
+    #[case("blog/2023-04-01_my-post.md", Some("2023-04-01"))]
+    #[case("blog/2023-04-01_my-post/index.md", Some("2023-04-01"))]
+    #[case("blog/2023-04-01_my-post/index.fr.md", Some("2023-04-01"))]
+    #[case("blog/2023-04-01_my-post/other.md", None)]
+    #[case("blog/2023-04-01.md", None)]
+    #[case("blog/my-post-2023-04-01.md", None)]
+    #[case("blog/2023-13-45-my-post.md", None)]
+    #[case("2023-04-01-blog/my-post.md", None)]
+    #[case("blog/2023-04-01T10:00:00Z-my-post.md", Some("2023-04-01T10:00:00Z"))]
+    #[case(
+        "blog/2023-04-01T10:00:00+02:00_my-post.md",
+        Some("2023-04-01T10:00:00+02:00")
+    )]
+    #[case(
+        "blog/2023-04-01T10:00:00.5-05:00-my-post/index.md",
+        Some("2023-04-01T10:00:00.5-05:00")
+    )]
+    #[case("blog/2023-04-01 -my-post.md", Some("2023-04-01"))]
+    #[case("blog/2023-04-01T10:00-my-post.md", None)]
+    #[case("blog/2023-04-01T25:00:00Z-my-post.md", None)]
+    fn dates_from_names(#[case] path: &str, #[case] expected: Option<&str>) {
+        let expected = expected.map(|s| s.parse().unwrap());
+        assert_eq!(filename_date(Path::new(path)), expected);
+    }
+}
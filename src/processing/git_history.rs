@@ -0,0 +1,1150 @@
+//! Collects the last edit date of every file in a repository using a single walk of the git history
+//!
+//! Renames are followed so that a file that was moved keeps the history from before it was moved.
+//! Commits that only rename a file (without changing the content) are not counted as edits unless moves are counted (See [`Cli::count_moves`]).
+//! Optionally commits that only change (part of) the front matter of a file are also not counted as edits.
+//! Patches for this are only requested for Markdown files and the span of the front matter is read from the blobs of each version.
+//! Commits can also be skipped based on their message (See [`SKIP_TRAILER_RE`] and [`Cli::skip_commit_pattern`]).
+//! The commit that added a file is never skipped.
+//!
+//! The date of the commit that first added each file (following renames) is also recorded.
+//!
+//! The date used for a commit is the committer date unless the author date is selected (See [`DateSource`]).
+//! Dates are in the time zone of each commit unless a time zone is set (See [`Cli::timezone`]).
+
+use std::{
+    collections::{HashMap, HashSet},
+    io::Write as _,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+};
+
+use anyhow::{bail, Context};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use tracing::{debug, warn};
+
+use crate::{
+    cli::{Cli, DateSource, FrontMatterPart, Precision, TimeZone},
+    error::Error,
+    site::is_bundle_file,
+};
+
+/// Separates commits in the output of `git log`
+const COMMIT_SEPARATOR: char = '\x1e';
+/// Separates fields in the header of each commit
+const FIELD_SEPARATOR: char = '\x1f';
+/// Limits the patches requested to pages
+const PAGES_PATHSPEC: &str = "*.md";
+
+/// Matches the trailer that marks a commit to be skipped (`Chrono-Skip: true`)
+static SKIP_TRAILER_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?mi)^chrono-skip:[ \t]*true[ \t]*$").unwrap());
+
+/// Matches lines (TOML or YAML) that set `date` or `updated`
+static DATE_LINE_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r#"^[ \t]*["']?(?:date|updated)["']?[ \t]*[=:]"#).unwrap());
+
+/// Options that control which commits are counted as edits
+#[derive(Debug, Default)]
+struct HistoryOptions {
+    skip_changing_only: Option<FrontMatterPart>,
+    skip_commit_patterns: Vec<Regex>,
+    count_moves: bool,
+    date_source: DateSource,
+    time_zone: Option<TimeZone>,
+}
+
+impl HistoryOptions {
+    fn new(cli: &Cli) -> anyhow::Result<Self> {
+        let skip_commit_patterns = cli
+            .skip_commit_pattern
+            .iter()
+            .map(|pattern| {
+                Regex::new(pattern)
+                    .with_context(|| format!("Invalid skip commit pattern: {pattern:?}"))
+            })
+            .collect::<anyhow::Result<_>>()?;
+        Ok(Self {
+            // With a time the commit of the dates set by the tool is always after them so it must not count as an edit
+            skip_changing_only: cli.skip_commits_changing_only.or(
+                match cli.precision.unwrap_or_default() {
+                    Precision::Date => None,
+                    Precision::LocalDatetime | Precision::OffsetDatetime => {
+                        Some(FrontMatterPart::Dates)
+                    }
+                },
+            ),
+            skip_commit_patterns,
+            count_moves: cli.count_moves,
+            date_source: cli.date_source.unwrap_or_default(),
+            time_zone: cli.timezone,
+        })
+    }
+
+    fn is_skipped_message(&self, message: &str) -> bool {
+        SKIP_TRAILER_RE.is_match(message)
+            || self
+                .skip_commit_patterns
+                .iter()
+                .any(|pattern| pattern.is_match(message))
+    }
+}
+
+/// The dates found in the history of a single file
+#[derive(Debug)]
+struct FileHistory {
+    /// The date of the most recent commit that edited the file
+    last_edit: chrono::DateTime<chrono::FixedOffset>,
+    /// The hash of the most recent commit that edited the file
+    last_edit_commit: String,
+    /// The date of the commit that added the file (None if not reached or in a shallow clone, where the oldest commit
+    /// fetched shows every file as added)
+    first_added: Option<chrono::DateTime<chrono::FixedOffset>>,
+}
+
+#[derive(Debug, Default)]
+pub struct GitHistory {
+    /// The history of each file keyed by absolute path
+    files: HashMap<PathBuf, FileHistory>,
+    /// The paths in `files` grouped by the folder they count towards (See [`GitHistory::newest_file_in`])
+    files_by_dir: HashMap<PathBuf, Vec<PathBuf>>,
+    /// If set dates are converted to this time zone, otherwise the time zone of the commit is used
+    time_zone: Option<TimeZone>,
+}
+
+/// The paths changed by each commit (keyed by hash) where the change only touched the part of the front matter being skipped
+type SkippableChanges = HashMap<String, HashSet<String>>;
+
+impl GitHistory {
+    /// Loads the history of the repository that contains `path`
+    pub fn load(path: &Path, cli: &Cli) -> anyhow::Result<Self> {
+        let options = HistoryOptions::new(cli)?;
+        let dir = if path.is_file() {
+            path.parent()
+                .context("Failed to get parent folder of file")?
+        } else {
+            path
+        };
+        let top_level = run_git(dir, &["rev-parse", "--show-toplevel"])?;
+        let top_level = PathBuf::from(top_level.trim())
+            .canonicalize()
+            .context("Failed to canonicalize repository root")?;
+
+        if run_git(dir, &["rev-parse", "--verify", "--quiet", "HEAD"]).is_err() {
+            debug!("No commits found in repository at {top_level:?}");
+            return Ok(Self {
+                time_zone: options.time_zone,
+                ..Default::default()
+            });
+        }
+
+        let date_placeholder = match options.date_source {
+            DateSource::Committer => "%cI",
+            DateSource::Author => "%aI",
+        };
+        let format = format!(
+            "--format={COMMIT_SEPARATOR}%H{FIELD_SEPARATOR}{date_placeholder}{FIELD_SEPARATOR}%B"
+        );
+        let output = run_git(
+            dir,
+            &[
+                "log",
+                &format,
+                "--raw",
+                "--no-abbrev",
+                "-z",
+                "-M",
+                "--date-order",
+            ],
+        )?;
+        let skippable = match options.skip_changing_only {
+            Some(part) => skippable_changes(dir, part)?,
+            None => SkippableChanges::new(),
+        };
+        let mut result = Self::from_log_output(&top_level, &output, &options, &skippable)?;
+        if run_git(dir, &["rev-parse", "--is-shallow-repository"])?.trim() == "true" {
+            if cli.date_from_first_commit {
+                warn!("Repository at {top_level:?} is a shallow clone so the commits that added the files are not known. `--date-from-first-commit` is ignored");
+            }
+            result.forget_first_added();
+        }
+        Ok(result)
+    }
+
+    /// Drops the dates of the commits that added the files (The history does not reach them)
+    fn forget_first_added(&mut self) {
+        for file in self.files.values_mut() {
+            file.first_added = None;
+        }
+    }
+
+    /// Builds the history from the output of `git log` (Expects children to come before their parents)
+    ///
+    /// Changes listed in `skippable` are not counted as edits (See [`skippable_changes`]).
+    fn from_log_output(
+        top_level: &Path,
+        output: &str,
+        options: &HistoryOptions,
+        skippable: &SkippableChanges,
+    ) -> anyhow::Result<Self> {
+        let mut result = Self {
+            time_zone: options.time_zone,
+            ..Default::default()
+        };
+        let mut tracker = RenameTracker::default();
+        for commit in output.split(COMMIT_SEPARATOR).filter(|s| !s.is_empty()) {
+            let (header, files) = commit
+                .split_once('\0')
+                .with_context(|| format!("Malformed commit in git log output: {commit:?}"))?;
+            let mut fields = header.splitn(3, FIELD_SEPARATOR);
+            let (Some(hash), Some(date), Some(message)) =
+                (fields.next(), fields.next(), fields.next())
+            else {
+                bail!("Malformed commit header in git log output: {header:?}");
+            };
+            let is_skipped_message = options.is_skipped_message(message);
+            if is_skipped_message {
+                debug!("Skipping commit {hash} because of its message");
+            }
+            let date = chrono::DateTime::parse_from_rfc3339(date)
+                .with_context(|| format!("Failed to parse date of commit {hash}: {date:?}"))?;
+            let changes = parse_changes(files.trim_start_matches('\n'))
+                .with_context(|| format!("Failed to parse changes of commit {hash}"))?;
+            let skippable_paths = skippable.get(hash);
+            for change in changes {
+                let is_skippable_patch = change
+                    .new_path()
+                    .is_some_and(|path| skippable_paths.is_some_and(|paths| paths.contains(path)));
+                let is_add = matches!(change, FileChange::Added(_) | FileChange::Copied { .. });
+                let Some((current_path, is_content_changed)) = tracker.process(change) else {
+                    continue;
+                };
+                let is_edit = is_content_changed || options.count_moves;
+                if is_edit && (is_add || !(is_skippable_patch || is_skipped_message)) {
+                    // Only the first time a file is seen is kept as that is the most recent commit
+                    let file = result
+                        .files
+                        .entry(top_level.join(current_path))
+                        .or_insert_with(|| FileHistory {
+                            last_edit: date,
+                            last_edit_commit: hash.to_string(),
+                            first_added: None,
+                        });
+                    if is_add {
+                        file.first_added = Some(date);
+                    }
+                }
+            }
+        }
+        result.index_by_dir();
+        Ok(result)
+    }
+
+    /// Groups the files by the folders they count towards (See [`history_folders`])
+    fn index_by_dir(&mut self) {
+        for path in self.files.keys() {
+            for dir in history_folders(path) {
+                self.files_by_dir
+                    .entry(dir.to_path_buf())
+                    .or_default()
+                    .push(path.clone());
+            }
+        }
+    }
+
+    /// Returns the timestamp of the last commit that changed `path`
+    pub fn last_edit(&self, path: &Path) -> Option<chrono::DateTime<chrono::FixedOffset>> {
+        let date = self.in_time_zone(&self.files.get(path)?.last_edit);
+        debug!("GitDate: {date:?} - {path:?}");
+        Some(date)
+    }
+
+    /// Returns the timestamp of the commit that added `path` (following renames)
+    pub fn first_commit(&self, path: &Path) -> Option<chrono::DateTime<chrono::FixedOffset>> {
+        let date = self.in_time_zone(&self.files.get(path)?.first_added?);
+        debug!("FirstGitDate: {date:?} - {path:?}");
+        Some(date)
+    }
+
+    /// Returns the hash of the most recent commit that edited `path`
+    pub fn last_edit_commit(&self, path: &Path) -> Option<&str> {
+        Some(&self.files.get(path)?.last_edit_commit)
+    }
+
+    /// Returns the file directly in `dir` accepted by `is_included` with the newest last edit
+    ///
+    /// Besides the files in `dir` itself only the pages of bundles directly in `dir` are considered, so files in
+    /// nested sections are left out.
+    pub fn newest_file_in(&self, dir: &Path, is_included: impl Fn(&Path) -> bool) -> Option<&Path> {
+        let (newest, _) = self
+            .files_by_dir
+            .get(dir)?
+            .iter()
+            .filter(|path| is_included(path))
+            .filter_map(|path| Some((path, self.files.get(path)?)))
+            .max_by_key(|(_, file)| file.last_edit)?;
+        debug!("NewestFile: {newest:?} - {dir:?}");
+        Some(newest)
+    }
+
+    fn in_time_zone(
+        &self,
+        dt: &chrono::DateTime<chrono::FixedOffset>,
+    ) -> chrono::DateTime<chrono::FixedOffset> {
+        match self.time_zone {
+            Some(time_zone) => time_zone.convert(dt),
+            None => *dt,
+        }
+    }
+}
+
+/// A change to a single file in a commit as reported by `git log --name-status`
+#[derive(Debug, PartialEq, Eq)]
+enum FileChange<'a> {
+    Added(&'a str),
+    Modified(&'a str),
+    Deleted(&'a str),
+    Renamed {
+        from: &'a str,
+        to: &'a str,
+        is_content_changed: bool,
+    },
+    Copied {
+        to: &'a str,
+    },
+}
+
+impl<'a> FileChange<'a> {
+    /// The path of the file after the change (None if deleted)
+    fn new_path(&self) -> Option<&'a str> {
+        match *self {
+            FileChange::Added(path) | FileChange::Modified(path) => Some(path),
+            FileChange::Renamed { to, .. } | FileChange::Copied { to } => Some(to),
+            FileChange::Deleted(_) => None,
+        }
+    }
+}
+
+/// Finds the changes to pages that only touched `part` of the front matter
+///
+/// Uses a second walk of the history limited to Markdown files with patches without context, then reads the blobs of
+/// the versions involved to find where their front matter ends.
+fn skippable_changes(dir: &Path, part: FrontMatterPart) -> anyhow::Result<SkippableChanges> {
+    let format = format!("--format={COMMIT_SEPARATOR}%H");
+    let output = git_output(
+        dir,
+        &[
+            "log",
+            &format,
+            "--raw",
+            "--no-abbrev",
+            "-z",
+            "-M",
+            "-p",
+            "--unified=0",
+            "--full-index",
+            "--",
+            PAGES_PATHSPEC,
+        ],
+        None,
+    )?;
+    // Only used to check lines in the front matter, anything else that is not valid UTF-8 does not matter
+    let output = String::from_utf8_lossy(&output);
+    // Patches that would be skipped if all the lines they change are in the front matter
+    let candidates: Vec<_> = page_patches(&output)?
+        .into_iter()
+        .filter(|(_, _, patch)| is_only_change_to(patch, part, usize::MAX, usize::MAX))
+        .collect();
+    let needed: HashSet<&str> = candidates
+        .iter()
+        .filter_map(|(_, _, patch)| blob_ids(patch))
+        .flat_map(|(old, new)| [old, new])
+        .filter(|id| id.bytes().any(|b| b != b'0'))
+        .collect();
+    let ends = if needed.is_empty() {
+        HashMap::new()
+    } else {
+        let input: String = needed.iter().map(|id| format!("{id}\n")).collect();
+        let blobs = git_output(dir, &["cat-file", "--batch"], Some(input.as_bytes()))?;
+        front_matter_ends(&blobs)
+    };
+    let mut result = SkippableChanges::new();
+    for (hash, path, patch) in candidates {
+        let Some((old, new)) = blob_ids(patch) else {
+            continue;
+        };
+        let end_of = |id: &str| ends.get(id).copied().unwrap_or(0);
+        if is_only_change_to(patch, part, end_of(old), end_of(new)) {
+            result
+                .entry(hash.to_string())
+                .or_default()
+                .insert(path.to_string());
+        }
+    }
+    Ok(result)
+}
+
+/// Splits the output of `git log --raw -z -p` into (commit hash, path after the change, patch) for each change
+fn page_patches(output: &str) -> anyhow::Result<Vec<(&str, &str, &str)>> {
+    let mut result = vec![];
+    for commit in output.split(COMMIT_SEPARATOR).filter(|s| !s.is_empty()) {
+        let (hash, files) = commit
+            .split_once('\0')
+            .with_context(|| format!("Malformed commit in git log output: {commit:?}"))?;
+        let files = files.trim_start_matches('\n');
+        let (raw, patch) = files.split_once("\0\0").unwrap_or((files, ""));
+        let changes = parse_changes(raw)
+            .with_context(|| format!("Failed to parse changes of commit {hash}"))?;
+        let patches = split_patches(patch);
+        if patches.len() != changes.len() {
+            warn!("Number of patches ({}) does not match number of changes ({}) for commit {hash}. All changes in commit will be counted as edits", patches.len(), changes.len());
+            continue;
+        }
+        for (change, patch) in changes.iter().zip(patches) {
+            if let Some(path) = change.new_path() {
+                result.push((hash, path, patch));
+            }
+        }
+    }
+    Ok(result)
+}
+
+/// Returns the ids of the old and new blobs from the `index` line of a patch (Requires `--full-index`)
+fn blob_ids(patch: &str) -> Option<(&str, &str)> {
+    patch
+        .lines()
+        .take_while(|line| !line.starts_with("@@"))
+        .find_map(|line| line.strip_prefix("index "))
+        .and_then(|ids| ids.split(' ').next())
+        .and_then(|ids| ids.split_once(".."))
+}
+
+/// Parses the output of `git cat-file --batch` into the line the front matter ends on for each blob
+fn front_matter_ends(output: &[u8]) -> HashMap<String, usize> {
+    let mut result = HashMap::new();
+    let mut rest = output;
+    while let Some(newline) = rest.iter().position(|&b| b == b'\n') {
+        let header = String::from_utf8_lossy(&rest[..newline]);
+        rest = &rest[newline + 1..];
+        // Header is "<id> <type> <size>" or "<id> missing" (For example in a shallow clone)
+        let mut fields = header.split(' ');
+        let (Some(id), Some(_), Some(size)) = (fields.next(), fields.next(), fields.next()) else {
+            continue;
+        };
+        let Some(content) = size.parse().ok().and_then(|size: usize| rest.get(..size)) else {
+            warn!("Unexpected output from git cat-file for blob {id}");
+            break;
+        };
+        result.insert(
+            id.to_string(),
+            front_matter_end(&String::from_utf8_lossy(content)),
+        );
+        // Skip the content and the newline after it
+        rest = rest.get(content.len() + 1..).unwrap_or_default();
+    }
+    result
+}
+
+/// Returns the (1 based) line number of the closing delimiter of the front matter (0 if there is no front matter)
+fn front_matter_end(content: &str) -> usize {
+    let mut lines = content
+        .lines()
+        .enumerate()
+        .skip_while(|(_, line)| line.trim().is_empty());
+    let Some(delimiter) = lines
+        .next()
+        .map(|(_, line)| line.trim())
+        .filter(|line| ["+++", "---"].contains(line))
+    else {
+        return 0;
+    };
+    lines
+        .find(|(_, line)| line.trim_end() == delimiter)
+        .map_or(0, |(i, _)| i + 1)
+}
+
+/// Parses the start lines of the old and new file from a hunk header (Example: "@@ -3,2 +3 @@ title")
+fn hunk_starts(line: &str) -> Option<(usize, usize)> {
+    let mut fields = line.strip_prefix("@@ ")?.split(' ');
+    let start = |field: Option<&str>, sign: char| -> Option<usize> {
+        let range = field?.strip_prefix(sign)?;
+        range.split(',').next()?.parse().ok()
+    };
+    Some((start(fields.next(), '-')?, start(fields.next(), '+')?))
+}
+
+/// Parses the NUL separated output of `--raw -z` for one commit
+fn parse_changes(s: &str) -> anyhow::Result<Vec<FileChange<'_>>> {
+    let mut result = vec![];
+    let mut fields = s.split('\0').filter(|s| !s.is_empty());
+    while let Some(meta) = fields.next() {
+        // Status is the last field of the metadata (Example: ":100644 100644 <sha> <sha> M")
+        let status = meta
+            .rsplit(' ')
+            .next()
+            .filter(|_| meta.starts_with(':'))
+            .with_context(|| format!("Unexpected raw diff entry: {meta:?}"))?;
+        let mut next_path = || {
+            fields
+                .next()
+                .with_context(|| format!("Missing path for status {status:?}"))
+        };
+        let change = match status.chars().next() {
+            Some('A') => FileChange::Added(next_path()?),
+            Some('M' | 'T') => FileChange::Modified(next_path()?),
+            Some('D') => FileChange::Deleted(next_path()?),
+            Some('R') => FileChange::Renamed {
+                from: next_path()?,
+                to: next_path()?,
+                is_content_changed: status != "R100",
+            },
+            Some('C') => {
+                let _from = next_path()?;
+                FileChange::Copied { to: next_path()? }
+            }
+            _ => bail!("Unexpected status: {status:?}"),
+        };
+        result.push(change);
+    }
+    Ok(result)
+}
+
+/// Splits the patch output of one commit into the patches for each file
+fn split_patches(patch: &str) -> Vec<&str> {
+    let mut starts: Vec<usize> = patch
+        .match_indices("diff --git ")
+        .map(|(i, _)| i)
+        .filter(|&i| i == 0 || patch.as_bytes()[i - 1] == b'\n')
+        .collect();
+    starts.push(patch.len());
+    starts.windows(2).map(|w| &patch[w[0]..w[1]]).collect()
+}
+
+/// Checks if every line added or removed by the patch for a file is in `part`
+///
+/// The patch must not have context lines (`--unified=0`). The front matter of the old and new versions of the file ends
+/// on the lines passed (including the delimiters, 0 if there is no front matter).
+fn is_only_change_to(patch: &str, part: FrontMatterPart, old_end: usize, new_end: usize) -> bool {
+    let mut old_line = 0;
+    let mut new_line = 0;
+    let mut hunk_count = 0;
+    let mut changed_count = 0;
+    for line in patch.lines() {
+        if line.starts_with("@@") {
+            let Some((old_start, new_start)) = hunk_starts(line) else {
+                return false;
+            };
+            (old_line, new_line) = (old_start, new_start);
+            hunk_count += 1;
+            continue;
+        }
+        if hunk_count == 0 {
+            // Still in the header
+            continue;
+        }
+        let (prefix, content) = line.split_at(line.len().min(1));
+        let is_in_front_matter = match prefix {
+            "-" => {
+                old_line += 1;
+                old_line - 1 <= old_end
+            }
+            "+" => {
+                new_line += 1;
+                new_line - 1 <= new_end
+            }
+            _ => continue, // "\ No newline at end of file"
+        };
+        let is_change_in_part = is_in_front_matter
+            && match part {
+                FrontMatterPart::FrontMatter => true,
+                FrontMatterPart::Dates => DATE_LINE_RE.is_match(content),
+            };
+        if !is_change_in_part {
+            return false;
+        }
+        changed_count += 1;
+    }
+    changed_count > 0
+}
+
+/// Maps paths found in older commits to the path the file has now
+///
+/// Commits must be processed from newest to oldest.
+#[derive(Debug, Default)]
+struct RenameTracker<'a> {
+    /// Maps a path as it was at the current point in history to the current path. `None` means the file at that path
+    /// (at this point in history) is not a file that exists now (Either deleted later or a different file was added later).
+    aliases: HashMap<&'a str, Option<&'a str>>,
+}
+
+impl<'a> RenameTracker<'a> {
+    /// Returns the current path of the file changed and if the change is an edit to the content.
+    /// Returns `None` if the file changed no longer exists under any name.
+    fn process(&mut self, change: FileChange<'a>) -> Option<(&'a str, bool)> {
+        match change {
+            FileChange::Added(path) | FileChange::Copied { to: path } => {
+                let current = self.current(path);
+                // Older commits that used this path were for a different file
+                self.aliases.insert(path, None);
+                current.map(|current| (current, true))
+            }
+            FileChange::Modified(path) => self.current(path).map(|current| (current, true)),
+            FileChange::Deleted(path) => {
+                // Older commits that used this path were for the deleted file
+                self.aliases.insert(path, None);
+                None
+            }
+            FileChange::Renamed {
+                from,
+                to,
+                is_content_changed,
+            } => {
+                let current = self.current(to);
+                self.aliases.insert(to, None);
+                self.aliases.insert(from, current);
+                current.map(|current| (current, is_content_changed))
+            }
+        }
+    }
+
+    fn current(&self, path: &'a str) -> Option<&'a str> {
+        match self.aliases.get(path) {
+            Some(alias) => *alias,
+            None => Some(path),
+        }
+    }
+}
+
+/// Returns the content of the file at `path` in the last commit (None if it is not in the last commit)
+pub(super) fn committed_content(path: &Path) -> anyhow::Result<Option<String>> {
+    let dir = path
+        .parent()
+        .context("Failed to get parent folder of file")?;
+    let name = path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .with_context(|| format!("File name is not valid UTF-8: {path:?}"))?;
+    if run_git(dir, &["ls-tree", "--name-only", "HEAD", "--", name])?
+        .trim()
+        .is_empty()
+    {
+        return Ok(None);
+    }
+    let content = git_output(dir, &["show", &format!("HEAD:./{name}")], None)?;
+    Ok(Some(String::from_utf8_lossy(&content).into_owned()))
+}
+
+/// The folders whose history `path` counts towards: its own and, for the page of a bundle, the folder containing the bundle
+pub(super) fn history_folders(path: &Path) -> impl Iterator<Item = &Path> {
+    let dir = path.parent();
+    let bundle_dir = dir.filter(|_| is_bundle_file(path)).and_then(Path::parent);
+    dir.into_iter().chain(bundle_dir)
+}
+
+/// Runs git in `dir` and returns stdout
+pub(super) fn run_git(dir: &Path, args: &[&str]) -> anyhow::Result<String> {
+    let output = git_output(dir, args, None)?;
+    String::from_utf8(output).context("git output was not valid UTF-8")
+}
+
+/// Runs git in `dir` with `input` (if any) written to stdin and returns stdout as bytes
+fn git_output(dir: &Path, args: &[&str], input: Option<&[u8]>) -> anyhow::Result<Vec<u8>> {
+    let spawn_error = |source: std::io::Error| {
+        // Starting a process in a missing folder also fails with `NotFound` so it must not be taken to mean git is missing
+        if source.kind() == std::io::ErrorKind::NotFound && !dir.is_dir() {
+            return Error::io(dir)(source);
+        }
+        Error::Git {
+            path: dir.to_path_buf(),
+            message: format!("Failed to execute git command. args: {args:?}"),
+            source: Some(source),
+        }
+    };
+    let mut child = Command::new("git")
+        .args(args)
+        .current_dir(dir)
+        .stdin(if input.is_some() {
+            Stdio::piped()
+        } else {
+            Stdio::null()
+        })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(spawn_error)?;
+    // Write from another thread so git is not blocked on a full stdout while we are still writing
+    let (output, written) = std::thread::scope(|scope| {
+        let writer = input
+            .zip(child.stdin.take())
+            .map(|(input, mut stdin)| scope.spawn(move || stdin.write_all(input)));
+        let output = child.wait_with_output();
+        let written = writer.map_or(Ok(()), |writer| {
+            writer.join().expect("Thread writing to git panicked")
+        });
+        (output, written)
+    });
+    let output = output.map_err(spawn_error)?;
+    if !output.status.success() {
+        return Err(Error::Git {
+            path: dir.to_path_buf(),
+            message: format!(
+                "args: {args:?} status: {} stdout: {}, stderr: {}",
+                output.status,
+                String::from_utf8_lossy(&output.stdout),
+                String::from_utf8_lossy(&output.stderr)
+            ),
+            source: None,
+        }
+        .into());
+    }
+    written.map_err(spawn_error)?;
+    if !output.stderr.is_empty() {
+        warn!(
+            "git wrote to stderr. args: {args:?} stderr: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+    Ok(output.stdout)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    /// Builds output in the same format as `git log --raw -z` from (hash, date, changes) where each change is the status
+    /// followed by the paths all separated by `|`
+    fn log_output(commits: &[(&str, &str, &[&str])]) -> String {
+        let commits: Vec<_> = commits
+            .iter()
+            .map(|&(hash, date, changes)| (hash, date, "msg\n", changes))
+            .collect();
+        log_output_with_messages(&commits)
+    }
+
+    /// Same as [`log_output`] but also takes the commit message (hash, date, message, changes)
+    fn log_output_with_messages(commits: &[(&str, &str, &str, &[&str])]) -> String {
+        let mut result = String::new();
+        for (hash, date, message, changes) in commits {
+            result.push_str(&format!(
+                "{COMMIT_SEPARATOR}{hash}{FIELD_SEPARATOR}{date}{FIELD_SEPARATOR}{message}\0\n"
+            ));
+            for change in changes.iter() {
+                let mut fields = change.split('|');
+                let status = fields.next().unwrap();
+                result.push_str(&format!(
+                    ":100644 100644 {0} {0} {status}\0",
+                    "0".repeat(40)
+                ));
+                for path in fields {
+                    result.push_str(path);
+                    result.push('\0');
+                }
+            }
+        }
+        result
+    }
+
+    fn date(year: i32, month: u32, day: u32) -> Option<chrono::NaiveDate> {
+        chrono::NaiveDate::from_ymd_opt(year, month, day)
+    }
+
+    /// Shorthands to only compare the dates of commits
+    impl GitHistory {
+        fn last_edit_date(&self, path: &Path) -> Option<chrono::NaiveDate> {
+            self.last_edit(path).map(|dt| dt.date_naive())
+        }
+
+        fn first_commit_date(&self, path: &Path) -> Option<chrono::NaiveDate> {
+            self.first_commit(path).map(|dt| dt.date_naive())
+        }
+    }
+
+    #[test]
+    fn parse_log_output() {
+        let output = log_output(&[
+            (
+                "aaa",
+                "2023-03-02T23:30:00-05:00",
+                &["A|new.md", "M|old.md"],
+            ),
+            (
+                "bbb",
+                "2023-01-01T10:00:00+00:00",
+                &["A|old.md", "A|dir/other file.md"],
+            ),
+        ]);
+        let top_level = PathBuf::from("/repo");
+        let history = GitHistory::from_log_output(
+            &top_level,
+            &output,
+            &Default::default(),
+            &Default::default(),
+        )
+        .unwrap();
+        assert_eq!(
+            history.last_edit_date(&top_level.join("new.md")),
+            date(2023, 3, 2)
+        );
+        assert_eq!(
+            history.last_edit_date(&top_level.join("old.md")),
+            date(2023, 3, 2)
+        );
+        assert_eq!(
+            history.last_edit_date(&top_level.join("dir/other file.md")),
+            date(2023, 1, 1)
+        );
+        assert_eq!(history.last_edit_date(&top_level.join("missing.md")), None);
+    }
+
+    #[test]
+    fn parse_renames() {
+        let output = log_output(&[(
+            "aaa",
+            "2023-01-01T00:00:00Z",
+            &[
+                "R100|a.md|b.md",
+                "R087|c.md|d.md",
+                "C090|e.md|f.md",
+                "D|g.md",
+            ],
+        )]);
+        let (_, changes) = output.split_once("\0\n").unwrap();
+        assert_eq!(
+            parse_changes(changes).unwrap(),
+            vec![
+                FileChange::Renamed {
+                    from: "a.md",
+                    to: "b.md",
+                    is_content_changed: false
+                },
+                FileChange::Renamed {
+                    from: "c.md",
+                    to: "d.md",
+                    is_content_changed: true
+                },
+                FileChange::Copied { to: "f.md" },
+                FileChange::Deleted("g.md"),
+            ]
+        );
+        assert!(parse_changes(":100644 100644 0 0 R100\0a.md\0").is_err());
+        assert!(parse_changes("R100\0a.md\0b.md\0").is_err());
+    }
+
+    #[test]
+    fn missing_folder_is_not_missing_git() {
+        let error = git_output(
+            Path::new("/non_existent_path_bfEHgMV62y5S7LYn"),
+            &["status"],
+            None,
+        )
+        .unwrap_err();
+        assert_eq!(Error::kind_of(&error), crate::ErrorKind::Io);
+    }
+
+    #[rstest]
+    #[case(false, date(2023, 2, 1))]
+    #[case(true, date(2023, 5, 1))]
+    fn follow_renames(
+        #[case] count_moves: bool,
+        #[case] expected_moved: Option<chrono::NaiveDate>,
+    ) {
+        // Newest first:
+        //  - 5: pure rename of `moved.md` from `tmp.md`
+        //  - 4: new `a.md` added after the original was moved away
+        //  - 3: `old.md` deleted
+        //  - 2: `a.md` renamed to `moved.md` (with changes)
+        //  - 1: `a.md` and `old.md` added
+        let output = log_output(&[
+            ("5", "2023-05-01T00:00:00Z", &["R100|tmp.md|moved.md"]),
+            ("4", "2023-04-01T00:00:00Z", &["A|a.md"]),
+            ("3", "2023-03-01T00:00:00Z", &["D|old.md"]),
+            ("2", "2023-02-01T00:00:00Z", &["R090|a.md|tmp.md"]),
+            ("1", "2023-01-01T00:00:00Z", &["A|a.md", "A|old.md"]),
+        ]);
+        let top_level = PathBuf::from("/repo");
+        let options = HistoryOptions {
+            count_moves,
+            ..Default::default()
+        };
+        let history =
+            GitHistory::from_log_output(&top_level, &output, &options, &Default::default())
+                .unwrap();
+        assert_eq!(
+            history.last_edit_date(&top_level.join("moved.md")),
+            expected_moved
+        );
+        assert_eq!(
+            history.last_edit_date(&top_level.join("a.md")),
+            date(2023, 4, 1)
+        );
+        assert_eq!(history.last_edit_date(&top_level.join("tmp.md")), None);
+        assert_eq!(
+            history.first_commit_date(&top_level.join("moved.md")),
+            date(2023, 1, 1)
+        );
+        assert_eq!(
+            history.first_commit_date(&top_level.join("a.md")),
+            date(2023, 4, 1)
+        );
+    }
+
+    const DATES_ONLY_PATCH: &str = "diff --git a/a.md b/a.md
+index 1111111111111111111111111111111111111111..2222222222222222222222222222222222222222 100644
+--- a/a.md
++++ b/a.md
+@@ -3 +3 @@ title = \"A\"
+-updated = 2023-01-01
++updated = 2023-02-01
+@@ -4,0 +5 @@
++date = 2020-01-01
+";
+
+    const TITLE_PATCH: &str = "diff --git a/a.md b/a.md
+index 1111111..2222222 100644
+--- a/a.md
++++ b/a.md
+@@ -2 +2 @@
+-title = \"A\"
++title = \"B\"
+";
+
+    const CONTENT_PATCH: &str = "diff --git a/a.md b/a.md
+index 1111111..2222222 100644
+--- a/a.md
++++ b/a.md
+@@ -6 +6 @@
+-Body
++New Body
+\\ No newline at end of file
+";
+
+    /// A line in the body that looks like it sets a date
+    const BODY_DATE_PATCH: &str = "diff --git a/a.md b/a.md
+index 1111111..2222222 100644
+--- a/a.md
++++ b/a.md
+@@ -8 +8 @@ Some text
+-date: TBD
++date: 2024-01-01
+";
+
+    #[rstest]
+    #[case(DATES_ONLY_PATCH, FrontMatterPart::Dates, 5, true)]
+    #[case(DATES_ONLY_PATCH, FrontMatterPart::Dates, 4, false)]
+    #[case(TITLE_PATCH, FrontMatterPart::Dates, 5, false)]
+    #[case(TITLE_PATCH, FrontMatterPart::FrontMatter, 5, true)]
+    #[case(TITLE_PATCH, FrontMatterPart::FrontMatter, 0, false)]
+    #[case(CONTENT_PATCH, FrontMatterPart::Dates, 5, false)]
+    #[case(CONTENT_PATCH, FrontMatterPart::FrontMatter, 5, false)]
+    #[case(BODY_DATE_PATCH, FrontMatterPart::Dates, 5, false)]
+    #[case(BODY_DATE_PATCH, FrontMatterPart::FrontMatter, 5, false)]
+    #[case(
+        "diff --git a/a.md b/a.md\nold mode 100644\nnew mode 100755\n",
+        FrontMatterPart::Dates,
+        5,
+        false
+    )]
+    fn only_change_to(
+        #[case] patch: &str,
+        #[case] part: FrontMatterPart,
+        #[case] front_matter_end: usize,
+        #[case] expected: bool,
+    ) {
+        assert_eq!(
+            is_only_change_to(patch, part, front_matter_end, front_matter_end),
+            expected
+        );
+    }
+
+    #[rstest]
+    #[case("+++\ntitle = \"A\"\n+++\nBody\n", 3)]
+    #[case("\n---\ntitle: A\ndate: 2020-01-01\n---   \n\n--- \n", 5)]
+    #[case("+++\ntitle = \"A\"\n", 0)]
+    #[case("# Title\n\nBody\n# Title\n", 0)]
+    #[case("", 0)]
+    fn front_matter_spans(#[case] content: &str, #[case] expected: usize) {
+        assert_eq!(front_matter_end(content), expected);
+    }
+
+    #[test]
+    fn parse_blobs() {
+        let output = b"aaa blob 14\n+++\na = 1\n+++\n\nbbb missing\nccc blob 4\nBody\n";
+        let ends = front_matter_ends(output);
+        assert_eq!(ends.get("aaa"), Some(&3));
+        assert_eq!(ends.get("bbb"), None);
+        assert_eq!(ends.get("ccc"), Some(&0));
+        assert_eq!(
+            blob_ids(DATES_ONLY_PATCH),
+            Some(("1".repeat(40).as_str(), "2".repeat(40).as_str()))
+        );
+    }
+
+    #[test]
+    fn skip_commits_changing_only_dates() {
+        // Patches come from a separate walk of the pages where the header is only the hash
+        let id = "0".repeat(40);
+        let patch_output = format!(
+            "{COMMIT_SEPARATOR}2\0\n:100644 100644 {id} {id} M\0a.md\0:100644 100644 {id} {id} M\0b.md\0\0{}{}",
+            // Patches are in the same order as the changes
+            CONTENT_PATCH,
+            DATES_ONLY_PATCH.replace("a.md", "b.md")
+        );
+        let patches = page_patches(&patch_output).unwrap();
+        assert_eq!(
+            patches
+                .iter()
+                .map(|&(hash, path, patch)| (
+                    hash,
+                    path,
+                    is_only_change_to(patch, FrontMatterPart::Dates, 5, 5)
+                ))
+                .collect::<Vec<_>>(),
+            [("2", "a.md", false), ("2", "b.md", true)]
+        );
+
+        let output = log_output(&[
+            ("2", "2023-02-01T00:00:00Z", &["M|a.md", "M|b.md"]),
+            ("1", "2023-01-01T00:00:00Z", &["A|a.md", "A|b.md"]),
+        ]);
+        let top_level = PathBuf::from("/repo");
+        let skippable =
+            SkippableChanges::from([("2".to_string(), HashSet::from(["b.md".to_string()]))]);
+        let history =
+            GitHistory::from_log_output(&top_level, &output, &Default::default(), &skippable)
+                .unwrap();
+        assert_eq!(
+            history.last_edit_date(&top_level.join("a.md")),
+            date(2023, 2, 1)
+        );
+        assert_eq!(
+            history.last_edit_date(&top_level.join("b.md")),
+            date(2023, 1, 1)
+        );
+
+        let history = GitHistory::from_log_output(
+            &top_level,
+            &output,
+            &Default::default(),
+            &Default::default(),
+        )
+        .unwrap();
+        assert_eq!(
+            history.last_edit_date(&top_level.join("b.md")),
+            date(2023, 2, 1)
+        );
+    }
+
+    #[test]
+    fn skip_commits_by_message() {
+        let output = log_output_with_messages(&[
+            ("4", "2023-04-01T00:00:00Z", "Fix typo\n", &["M|a.md"]),
+            (
+                "3",
+                "2023-03-01T00:00:00Z",
+                "Reformat\n\nChrono-Skip: true\n",
+                &["M|a.md", "M|b.md", "A|c.md"],
+            ),
+            ("2", "2023-02-01T00:00:00Z", "Edit\n", &["M|a.md", "M|b.md"]),
+            ("1", "2023-01-01T00:00:00Z", "Add\n", &["A|a.md", "A|b.md"]),
+        ]);
+        let top_level = PathBuf::from("/repo");
+        let options = HistoryOptions {
+            skip_commit_patterns: vec![Regex::new("(?i)typo").unwrap()],
+            ..Default::default()
+        };
+        let history =
+            GitHistory::from_log_output(&top_level, &output, &options, &Default::default())
+                .unwrap();
+        assert_eq!(
+            history.last_edit_date(&top_level.join("a.md")),
+            date(2023, 2, 1)
+        );
+        assert_eq!(
+            history.last_edit_date(&top_level.join("b.md")),
+            date(2023, 2, 1)
+        );
+        // Adding a file is never skipped
+        assert_eq!(
+            history.last_edit_date(&top_level.join("c.md")),
+            date(2023, 3, 1)
+        );
+    }
+
+    #[rstest]
+    #[case(None, date(2023, 1, 1))]
+    #[case(Some("UTC"), date(2023, 1, 2))]
+    #[case(Some("-08:00"), date(2023, 1, 1))]
+    #[case(Some("Asia/Tokyo"), date(2023, 1, 2))]
+    fn dates_in_time_zone(
+        #[case] time_zone: Option<&str>,
+        #[case] expected: Option<chrono::NaiveDate>,
+    ) {
+        let output = log_output(&[("1", "2023-01-01T20:00:00-05:00", &["A|a.md"])]);
+        let top_level = PathBuf::from("/repo");
+        let options = HistoryOptions {
+            time_zone: time_zone.map(|tz| tz.parse().unwrap()),
+            ..Default::default()
+        };
+        let history =
+            GitHistory::from_log_output(&top_level, &output, &options, &Default::default())
+                .unwrap();
+        assert_eq!(history.last_edit_date(&top_level.join("a.md")), expected);
+        assert_eq!(history.first_commit_date(&top_level.join("a.md")), expected);
+    }
+
+    #[test]
+    fn newest_file_in_folder() {
+        let output = log_output(&[
+            (
+                "3",
+                "2023-03-01T00:00:00Z",
+                &["M|blog/_index.md", "M|about.md"],
+            ),
+            ("2", "2023-02-01T00:00:00Z", &["M|blog/2023/b.md"]),
+            ("4", "2023-01-20T00:00:00Z", &["M|blog/post/image.png"]),
+            ("5", "2023-01-10T00:00:00Z", &["M|blog/post/index.md"]),
+            (
+                "1",
+                "2023-01-01T00:00:00Z",
+                &[
+                    "A|blog/_index.md",
+                    "A|blog/a.md",
+                    "A|blog/post/index.md",
+                    "A|blog/post/image.png",
+                    "A|blog/2023/_index.md",
+                    "A|blog/2023/b.md",
+                    "A|about.md",
+                ],
+            ),
+        ]);
+        let top_level = PathBuf::from("/repo");
+        let history = GitHistory::from_log_output(
+            &top_level,
+            &output,
+            &Default::default(),
+            &Default::default(),
+        )
+        .unwrap();
+        let is_page = |path: &Path| !path.ends_with("_index.md");
+        let newest = |dir: &str| history.newest_file_in(&top_level.join(dir), is_page);
+        let expected = top_level.join("blog/2023/b.md");
+        // Pages in the nested section and bundle assets are not direct children but the bundle's page is
+        assert_eq!(
+            newest("blog"),
+            Some(top_level.join("blog/post/index.md").as_path())
+        );
+        assert_eq!(newest("blog/2023"), Some(expected.as_path()));
+        assert_eq!(
+            newest("blog/post"),
+            Some(top_level.join("blog/post/image.png").as_path())
+        );
+        assert_eq!(newest("docs"), None);
+        assert_eq!(history.last_edit_commit(&expected), Some("2"));
+        assert_eq!(
+            history.last_edit_commit(&top_level.join("about.md")),
+            Some("3")
+        );
+    }
+}
@@ -0,0 +1,122 @@
+//! Decides which files are skipped based on the include and exclude glob patterns
+//!
+//! Patterns are matched against the path relative to the folder being processed and relative to the root of the zola site (if found).
+//! So both `archive/**` and `content/archive/**` work when processing the content folder of a site.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+
+use crate::{cli::Cli, stats::SkipReason};
+
+#[derive(Debug)]
+pub struct PathFilter {
+    /// None if no include patterns were provided (everything is included)
+    include: Option<GlobSet>,
+    exclude: GlobSet,
+    /// Folders that patterns are relative to
+    bases: Vec<PathBuf>,
+}
+
+impl PathFilter {
+    pub fn new(cli: &Cli, bases: Vec<PathBuf>) -> anyhow::Result<Self> {
+        let include = if cli.include.is_empty() {
+            None
+        } else {
+            Some(build_glob_set(&cli.include).context("Invalid include pattern")?)
+        };
+        let exclude = build_glob_set(&cli.exclude).context("Invalid exclude pattern")?;
+        Ok(Self {
+            include,
+            exclude,
+            bases,
+        })
+    }
+
+    /// Returns the reason to skip `path` if the patterns say it should be skipped
+    pub fn skip_reason(&self, path: &Path) -> Option<SkipReason> {
+        let relative_paths: Vec<&Path> = self
+            .bases
+            .iter()
+            .filter_map(|base| path.strip_prefix(base).ok())
+            .collect();
+        if relative_paths
+            .iter()
+            .any(|relative| self.exclude.is_match(relative))
+        {
+            return Some(SkipReason::Excluded);
+        }
+        match &self.include {
+            Some(include)
+                if !relative_paths
+                    .iter()
+                    .any(|relative| include.is_match(relative)) =>
+            {
+                Some(SkipReason::NotIncluded)
+            }
+            _ => None,
+        }
+    }
+}
+
+fn build_glob_set(patterns: &[String]) -> anyhow::Result<GlobSet> {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        // Like gitignore `*` does not match `/` but `**` does
+        let glob = GlobBuilder::new(pattern)
+            .literal_separator(true)
+            .build()
+            .with_context(|| format!("Failed to parse glob: {pattern:?}"))?;
+        builder.add(glob);
+    }
+    Ok(builder.build()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    fn filter(include: &[&str], exclude: &[&str]) -> PathFilter {
+        let cli = Cli {
+            include: include.iter().map(|s| s.to_string()).collect(),
+            exclude: exclude.iter().map(|s| s.to_string()).collect(),
+            ..Default::default()
+        };
+        PathFilter::new(
+            &cli,
+            vec![PathBuf::from("/site/content"), PathBuf::from("/site")],
+        )
+        .unwrap()
+    }
+
+    #[rstest]
+    #[case(&[], &[], "/site/content/blog/a.md", None)]
+    #[case(&[], &["archive/**"], "/site/content/archive/2020/a.md", Some(SkipReason::Excluded))]
+    #[case(&[], &["content/archive/**"], "/site/content/archive/a.md", Some(SkipReason::Excluded))]
+    #[case(&[], &["**/drafts/**"], "/site/content/blog/drafts/a.md", Some(SkipReason::Excluded))]
+    #[case(&[], &["**/README.md"], "/site/content/README.md", Some(SkipReason::Excluded))]
+    #[case(&[], &["*.md"], "/site/content/blog/a.md", None)]
+    #[case(&["blog/**"], &[], "/site/content/blog/a.md", None)]
+    #[case(&["blog/**"], &[], "/site/content/about.md", Some(SkipReason::NotIncluded))]
+    #[case(&["blog/**"], &["blog/old/**"], "/site/content/blog/old/a.md", Some(SkipReason::Excluded))]
+    fn skip_reason(
+        #[case] include: &[&str],
+        #[case] exclude: &[&str],
+        #[case] path: &str,
+        #[case] expected: Option<SkipReason>,
+    ) {
+        let actual = filter(include, exclude).skip_reason(Path::new(path));
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn invalid_pattern() {
+        let cli = Cli {
+            exclude: vec!["a/[".to_string()],
+            ..Default::default()
+        };
+        assert!(PathFilter::new(&cli, vec![]).is_err());
+    }
+}
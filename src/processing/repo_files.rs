@@ -0,0 +1,128 @@
+//! Lists the files git would consider part of the working tree so that ignored files are not processed
+//!
+//! Uses `git ls-files` so `.gitignore`, `.git/info/exclude` and the global excludes file are all honoured the same way git does.
+//! Folders that do not contain any listed files (like `node_modules` or `.git`) are not walked at all.
+
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+
+use super::git_history::run_git;
+use crate::stats::SkipReason;
+
+#[derive(Debug, Default)]
+pub struct RepoFiles {
+    /// Files that should be processed
+    files: HashSet<PathBuf>,
+    /// Files that are not ignored but also not tracked (Only filled when processing tracked files only)
+    untracked: HashSet<PathBuf>,
+    /// Folders that contain at least one of the files above
+    dirs: HashSet<PathBuf>,
+}
+
+impl RepoFiles {
+    /// Lists the files under `path` (Untracked files that are not ignored are included unless `tracked_only` is set)
+    pub fn load(path: &Path, tracked_only: bool) -> anyhow::Result<Self> {
+        let dir = if path.is_file() {
+            path.parent()
+                .context("Failed to get parent folder of file")?
+        } else {
+            path
+        };
+        let tracked = run_git(dir, &["ls-files", "-z", "--cached"])?;
+        let untracked = run_git(dir, &["ls-files", "-z", "--others", "--exclude-standard"])?;
+        Ok(Self::from_ls_files_output(
+            dir,
+            &tracked,
+            &untracked,
+            tracked_only,
+        ))
+    }
+
+    fn from_ls_files_output(
+        dir: &Path,
+        tracked: &str,
+        untracked: &str,
+        tracked_only: bool,
+    ) -> Self {
+        let mut result = Self::default();
+        let parse = |output: &str| -> Vec<PathBuf> {
+            output
+                .split('\0')
+                .filter(|s| !s.is_empty())
+                .map(|s| dir.join(s))
+                .collect()
+        };
+        result.files.extend(parse(tracked));
+        if tracked_only {
+            result.untracked.extend(parse(untracked));
+        } else {
+            result.files.extend(parse(untracked));
+        }
+        for file in result.files.iter().chain(result.untracked.iter()) {
+            for ancestor in file.ancestors().skip(1) {
+                if !result.dirs.insert(ancestor.to_path_buf()) {
+                    // Already added this folder so its ancestors are also already added
+                    break;
+                }
+            }
+        }
+        result
+    }
+
+    /// Checks if a folder contains any files that might be processed
+    pub fn should_visit_dir(&self, dir: &Path) -> bool {
+        self.dirs.contains(dir)
+    }
+
+    /// Returns the reason to skip `path` if git does not consider it part of the files to process
+    pub fn skip_reason(&self, path: &Path) -> Option<SkipReason> {
+        if self.files.contains(path) {
+            None
+        } else if self.untracked.contains(path) {
+            Some(SkipReason::Untracked)
+        } else {
+            Some(SkipReason::Ignored)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    const TRACKED: &str = "a.md\0blog/b.md\0";
+    const UNTRACKED: &str = "blog/new.md\0drafts/c.md\0";
+
+    #[rstest]
+    #[case(false, "/repo/a.md", None)]
+    #[case(false, "/repo/blog/new.md", None)]
+    #[case(false, "/repo/blog/ignored.md", Some(SkipReason::Ignored))]
+    #[case(true, "/repo/blog/b.md", None)]
+    #[case(true, "/repo/blog/new.md", Some(SkipReason::Untracked))]
+    #[case(true, "/repo/blog/ignored.md", Some(SkipReason::Ignored))]
+    fn skip_reason(
+        #[case] tracked_only: bool,
+        #[case] path: &str,
+        #[case] expected: Option<SkipReason>,
+    ) {
+        let files =
+            RepoFiles::from_ls_files_output(Path::new("/repo"), TRACKED, UNTRACKED, tracked_only);
+        assert_eq!(files.skip_reason(Path::new(path)), expected);
+    }
+
+    #[rstest]
+    #[case("/repo", true)]
+    #[case("/repo/blog", true)]
+    #[case("/repo/drafts", true)]
+    #[case("/repo/node_modules", false)]
+    #[case("/repo/.git", false)]
+    fn visit_dirs(#[case] dir: &str, #[case] expected: bool) {
+        let files = RepoFiles::from_ls_files_output(Path::new("/repo"), TRACKED, UNTRACKED, true);
+        assert_eq!(files.should_visit_dir(Path::new(dir)), expected);
+    }
+}
@@ -0,0 +1,145 @@
+//! Lists the files staged for the next commit so that only they are processed (Used by `--staged`)
+//!
+//! Paths from `git diff` are relative to the top level of the repository regardless of the folder git is run in.
+
+use std::{
+    collections::{BTreeSet, HashSet},
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+
+use super::git_history::{history_folders, run_git};
+
+#[derive(Debug, Default)]
+pub struct StagedFiles {
+    /// Top level folder of the repository
+    top_level: PathBuf,
+    /// Files with staged changes (Deleted files are not included)
+    files: BTreeSet<PathBuf>,
+    /// Files with changes that are not staged
+    unstaged: HashSet<PathBuf>,
+}
+
+impl StagedFiles {
+    /// Lists the staged files in the repository that contains `path`
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let dir = if path.is_file() {
+            path.parent()
+                .context("Failed to get parent folder of file")?
+        } else {
+            path
+        };
+        let top_level = run_git(dir, &["rev-parse", "--show-toplevel"])?;
+        let top_level = PathBuf::from(top_level.trim())
+            .canonicalize()
+            .context("Failed to canonicalize repository root")?;
+        let staged = run_git(
+            dir,
+            &[
+                "diff",
+                "--cached",
+                "--name-only",
+                "-z",
+                "--diff-filter=ACMR",
+            ],
+        )?;
+        let unstaged = run_git(dir, &["diff", "--name-only", "-z"])?;
+        Ok(Self::from_diff_output(top_level, &staged, &unstaged))
+    }
+
+    fn from_diff_output(top_level: PathBuf, staged: &str, unstaged: &str) -> Self {
+        let parse = |output: &str| -> Vec<PathBuf> {
+            output
+                .split('\0')
+                .filter(|s| !s.is_empty())
+                .map(|s| top_level.join(s))
+                .collect()
+        };
+        let files = parse(staged).into_iter().collect();
+        let unstaged = parse(unstaged).into_iter().collect();
+        Self {
+            top_level,
+            files,
+            unstaged,
+        }
+    }
+
+    /// The staged files under `path` (In sorted order)
+    pub fn files_under<'a>(&'a self, path: &'a Path) -> impl Iterator<Item = &'a Path> + 'a {
+        self.files
+            .iter()
+            .filter(move |file| file.starts_with(path))
+            .map(PathBuf::as_path)
+    }
+
+    /// The staged files that count towards the history of `dir` (See [`history_folders`])
+    pub fn files_in<'a>(&'a self, dir: &'a Path) -> impl Iterator<Item = &'a Path> + 'a {
+        self.files
+            .iter()
+            .map(PathBuf::as_path)
+            .filter(move |file| history_folders(file).any(|folder| folder == dir))
+    }
+
+    /// Checks if `path` has staged changes
+    pub fn contains(&self, path: &Path) -> bool {
+        self.files.contains(path)
+    }
+
+    /// Checks if `path` has changes that are not staged (Rewriting and staging it would also stage those)
+    pub fn has_unstaged_changes(&self, path: &Path) -> bool {
+        self.unstaged.contains(path)
+    }
+
+    /// Stages the current content of `paths`
+    pub fn stage(&self, paths: &[&Path]) -> anyhow::Result<()> {
+        if paths.is_empty() {
+            return Ok(());
+        }
+        let mut args = vec!["add", "--"];
+        for path in paths {
+            args.push(
+                path.to_str()
+                    .with_context(|| format!("Path is not valid UTF-8: {path:?}"))?,
+            );
+        }
+        run_git(&self.top_level, &args)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn staged_and_unstaged() {
+        let staged = StagedFiles::from_diff_output(
+            "/repo".into(),
+            "content/a.md\0content/blog/b.md\0content/blog/post/index.md\0static/c.png\0",
+            "content/blog/b.md\0content/d.md\0",
+        );
+        let under_content: Vec<_> = staged.files_under(Path::new("/repo/content")).collect();
+        assert_eq!(
+            under_content,
+            [
+                Path::new("/repo/content/a.md"),
+                Path::new("/repo/content/blog/b.md"),
+                Path::new("/repo/content/blog/post/index.md")
+            ]
+        );
+        let in_blog: Vec<_> = staged.files_in(Path::new("/repo/content/blog")).collect();
+        assert_eq!(
+            in_blog,
+            [
+                Path::new("/repo/content/blog/b.md"),
+                Path::new("/repo/content/blog/post/index.md")
+            ]
+        );
+        assert!(staged.contains(Path::new("/repo/static/c.png")));
+        assert!(!staged.contains(Path::new("/repo/content/d.md")));
+        assert!(staged.has_unstaged_changes(Path::new("/repo/content/blog/b.md")));
+        assert!(!staged.has_unstaged_changes(Path::new("/repo/content/a.md")));
+        assert!(staged.has_unstaged_changes(Path::new("/repo/content/d.md")));
+    }
+}
@@ -0,0 +1,154 @@
+//! Detects the zola site that contains a path and reads the parts of its `config.toml` that affect processing
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+use tracing::debug;
+
+/// Name of zola's configuration file
+pub const ZOLA_CONFIG_FILE_NAME: &str = "config.toml";
+/// Name of the folder zola reads pages from
+const CONTENT_DIR_NAME: &str = "content";
+
+/// A zola site found by looking for its `config.toml`
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct ZolaSite {
+    /// The folder that contains `config.toml`
+    root: PathBuf,
+    /// Codes of the languages other than the default language (Used in file names like `_index.fr.md`)
+    languages: Vec<String>,
+}
+
+impl ZolaSite {
+    /// Searches `start` and its ancestors for a zola `config.toml`
+    ///
+    /// Files named `config.toml` that do not look like a zola config (no `base_url`) are skipped.
+    pub fn find(start: &Path) -> anyhow::Result<Option<Self>> {
+        for dir in start.ancestors() {
+            let path = dir.join(ZOLA_CONFIG_FILE_NAME);
+            if !path.is_file() {
+                continue;
+            }
+            let text = fs::read_to_string(&path)
+                .with_context(|| format!("Failed to read zola config: {path:?}"))?;
+            let Ok(doc) = text.parse::<toml_edit::DocumentMut>() else {
+                debug!("Ignoring {path:?} as it is not valid TOML");
+                continue;
+            };
+            if doc.get("base_url").is_none() {
+                debug!("Ignoring {path:?} as it does not set `base_url`");
+                continue;
+            }
+            let result = Self::from_config(dir.to_path_buf(), &doc);
+            debug!("Found zola site: {result:?}");
+            return Ok(Some(result));
+        }
+        Ok(None)
+    }
+
+    fn from_config(root: PathBuf, doc: &toml_edit::DocumentMut) -> Self {
+        let default_language = doc.get("default_language").and_then(|item| item.as_str());
+        let mut languages: Vec<String> = match doc.get("languages") {
+            // Current format: `[languages.fr]`
+            Some(toml_edit::Item::Table(table)) => {
+                table.iter().map(|(code, _)| code.to_string()).collect()
+            }
+            // Older format: `languages = [{ code = "fr" }]`
+            Some(item) => item
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter_map(|language| language.as_inline_table()?.get("code")?.as_str())
+                .map(String::from)
+                .collect(),
+            None => vec![],
+        };
+        languages.retain(|code| Some(code.as_str()) != default_language);
+        Self { root, languages }
+    }
+
+    /// The folder that contains `config.toml`
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// The folder that contains the pages of the site
+    pub fn content_dir(&self) -> PathBuf {
+        self.root.join(CONTENT_DIR_NAME)
+    }
+
+    /// Checks if `path` is a section file (`_index.md` or `_index.<lang>.md` for a language of the site)
+    pub fn is_section_file(&self, path: &Path) -> bool {
+        is_section_file(path, &self.languages)
+    }
+}
+
+/// Checks if `path` is `_index.md` or `_index.<lang>.md` for one of the `languages`
+pub fn is_section_file(path: &Path, languages: &[String]) -> bool {
+    let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+        return false;
+    };
+    match name
+        .strip_prefix("_index")
+        .and_then(|rest| rest.strip_suffix(".md"))
+    {
+        Some("") => true,
+        Some(rest) => rest
+            .strip_prefix('.')
+            .is_some_and(|code| languages.iter().any(|language| language == code)),
+        None => false,
+    }
+}
+
+/// Checks if `path` is the page of a page bundle (`index.md` or `index.<lang>.md`)
+pub fn is_bundle_file(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .is_some_and(|name| name.starts_with("index.") && name.ends_with(".md"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case("base_url = \"/\"", &[])]
+    #[case(
+        "base_url = \"/\"\ndefault_language = \"en\"\n[languages.fr]\ntitle = \"Salut\"\n[languages.de]\n",
+        &["fr", "de"]
+    )]
+    #[case(
+        "base_url = \"/\"\ndefault_language = \"en\"\nlanguages = [{ code = \"fr\" }, { code = \"en\" }]",
+        &["fr"]
+    )]
+    fn languages_from_config(#[case] config: &str, #[case] expected: &[&str]) {
+        let doc = config.parse::<toml_edit::DocumentMut>().unwrap();
+        let site = ZolaSite::from_config(PathBuf::from("/site"), &doc);
+        assert_eq!(site.languages, expected);
+    }
+
+    #[rstest]
+    #[case("content/_index.md", true)]
+    #[case("content/_index.fr.md", true)]
+    #[case("content/_index.es.md", false)]
+    #[case("content/page.fr.md", false)]
+    #[case("content/my_index.md", false)]
+    fn section_files(#[case] path: &str, #[case] expected: bool) {
+        let languages = vec!["fr".to_string()];
+        assert_eq!(is_section_file(Path::new(path), &languages), expected);
+    }
+
+    #[rstest]
+    #[case("content/post/index.md", true)]
+    #[case("content/post/index.fr.md", true)]
+    #[case("content/post/_index.md", false)]
+    #[case("content/post/index.html", false)]
+    #[case("content/post/my_index.md", false)]
+    fn bundle_files(#[case] path: &str, #[case] expected: bool) {
+        assert_eq!(is_bundle_file(Path::new(path)), expected);
+    }
+}
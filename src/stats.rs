@@ -1,15 +1,134 @@
 //! Information Related to Run Statistics
 
-use std::{fmt::Display, ops::AddAssign};
+use std::{collections::BTreeMap, fmt::Display, ops::AddAssign, path::PathBuf};
 
-/// Tracks files changed (NB: Stops counting at 2^16 and saturates)
-#[derive(Debug, Default)]
+use serde::Serialize;
+
+use crate::error::{Error, ErrorKind};
+
+/// Tracks what happened to each file and the totals for each outcome
+#[derive(Debug, Default, Serialize)]
 #[must_use]
 pub struct Stats {
-    changed: u16,
-    not_changed: u16,
-    skipped: u16,
-    errors: u16,
+    changed: u64,
+    not_changed: u64,
+    skipped: u64,
+    skipped_by_reason: BTreeMap<SkipReason, u64>,
+    errors: u64,
+    /// What happened to each file (In the order they were visited)
+    files: Vec<FileRecord>,
+}
+
+/// What happened to a single file during a run
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FileRecord {
+    /// Path of the file
+    pub path: PathBuf,
+    /// The outcome for the file
+    pub status: FileStatus,
+    /// Why the file was skipped (Only set if skipped)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub skip_reason: Option<SkipReason>,
+    /// `date` as it was before processing
+    pub old_date: Option<String>,
+    /// `updated` as it was before processing
+    pub old_updated: Option<String>,
+    /// `date` after processing (Same as `old_date` if not changed)
+    pub new_date: Option<String>,
+    /// `updated` after processing (Same as `old_updated` if not changed)
+    pub new_updated: Option<String>,
+    /// The kind of error that stopped the file from being processed (Only set if there was an error)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error_kind: Option<ErrorKind>,
+    /// The error that stopped the file from being processed followed by its causes (Empty unless there was an error)
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub error_chain: Vec<String>,
+    /// Unified diff of the changes to the file (Only set for changed files when diffs were requested)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub diff: Option<String>,
+}
+
+/// The outcome for a single file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FileStatus {
+    /// The front matter was (or in check mode would have been) changed
+    Changed,
+    /// The front matter was already correct
+    NotChanged,
+    /// The file was not processed
+    Skipped,
+    /// Processing the file failed
+    Error,
+}
+
+impl FileRecord {
+    /// A record for a file that was skipped for `reason`
+    pub(crate) fn skipped(path: PathBuf, reason: SkipReason) -> Self {
+        Self {
+            path,
+            status: FileStatus::Skipped,
+            skip_reason: Some(reason),
+            old_date: None,
+            old_updated: None,
+            new_date: None,
+            new_updated: None,
+            error_kind: None,
+            error_chain: vec![],
+            diff: None,
+        }
+    }
+
+    /// A record for a file that failed with `error`
+    pub(crate) fn error(path: PathBuf, error: &anyhow::Error) -> Self {
+        Self {
+            path,
+            status: FileStatus::Error,
+            skip_reason: None,
+            old_date: None,
+            old_updated: None,
+            new_date: None,
+            new_updated: None,
+            error_kind: Some(Error::kind_of(error)),
+            error_chain: error.chain().map(ToString::to_string).collect(),
+            diff: None,
+        }
+    }
+}
+
+/// The reason a file was not processed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SkipReason {
+    /// Not a markdown file
+    NotMarkdown,
+    /// A section file (`_index.md`)
+    SectionFile,
+    /// Matched one of the exclude patterns
+    Excluded,
+    /// Include patterns were set and the file did not match any of them
+    NotIncluded,
+    /// Ignored by git (`.gitignore`, `.git/info/exclude` or global excludes)
+    Ignored,
+    /// Not tracked by git and only tracked files were to be processed
+    Untracked,
+    /// A draft page (`draft = true`) and drafts were to be skipped
+    Draft,
+}
+
+impl Display for SkipReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            SkipReason::NotMarkdown => "Not Markdown",
+            SkipReason::SectionFile => "Section File",
+            SkipReason::Excluded => "Excluded",
+            SkipReason::NotIncluded => "Not Included",
+            SkipReason::Ignored => "Ignored",
+            SkipReason::Untracked => "Untracked",
+            SkipReason::Draft => "Draft",
+        };
+        write!(f, "{s}")
+    }
 }
 
 impl Stats {
@@ -18,48 +137,80 @@ impl Stats {
             changed: 0,
             not_changed: 0,
             skipped: 0,
+            skipped_by_reason: BTreeMap::new(),
             errors: 0,
+            files: vec![],
         }
     }
 
     /// Gets the current value of `changed`
-    pub fn changed(&self) -> u16 {
+    pub fn changed(&self) -> u64 {
         self.changed
     }
 
     /// Gets the current value of `not_changed`
-    pub fn not_changed(&self) -> u16 {
+    pub fn not_changed(&self) -> u64 {
         self.not_changed
     }
 
     /// Gets the current value of `skipped`
-    pub fn skipped(&self) -> u16 {
+    pub fn skipped(&self) -> u64 {
         self.skipped
     }
 
+    /// Gets the number of files skipped for `reason`
+    pub fn skipped_for(&self, reason: SkipReason) -> u64 {
+        self.skipped_by_reason
+            .get(&reason)
+            .copied()
+            .unwrap_or_default()
+    }
+
     /// Gets the current value of `errors`
-    pub fn errors(&self) -> u16 {
+    pub fn errors(&self) -> u64 {
         self.errors
     }
 
-    /// Increments `changed` by 1 (saturating if applicable)
-    pub fn inc_changed(&mut self) {
-        self.changed = self.changed.saturating_add(1);
+    /// Gets what happened to each file
+    pub fn files(&self) -> &[FileRecord] {
+        &self.files
+    }
+
+    /// Adds the record of a file and increments the counter that matches its status
+    pub(crate) fn add_file(&mut self, record: FileRecord) {
+        match record.status {
+            FileStatus::Changed => self.inc_changed(),
+            FileStatus::NotChanged => self.inc_not_changed(),
+            FileStatus::Skipped => self.inc_skipped(
+                record
+                    .skip_reason
+                    .expect("skipped records are always created with a reason"),
+            ),
+            FileStatus::Error => self.inc_errors(),
+        }
+        self.files.push(record);
     }
 
-    /// Increments `not_changed`` by 1 (saturating if applicable)
-    pub fn inc_not_changed(&mut self) {
-        self.not_changed = self.not_changed.saturating_add(1);
+    /// Increments `changed` by 1
+    fn inc_changed(&mut self) {
+        self.changed += 1;
     }
 
-    /// Increments `skipped` by 1 (saturating if applicable)
-    pub fn inc_skipped(&mut self) {
-        self.skipped = self.skipped.saturating_add(1);
+    /// Increments `not_changed` by 1
+    fn inc_not_changed(&mut self) {
+        self.not_changed += 1;
     }
 
-    /// Increments `errors` by 1 (saturating if applicable)
-    pub fn inc_errors(&mut self) {
-        self.errors = self.errors.saturating_add(1);
+    /// Increments `skipped` and the count for `reason` by 1
+    fn inc_skipped(&mut self, reason: SkipReason) {
+        self.skipped += 1;
+        let count = self.skipped_by_reason.entry(reason).or_default();
+        *count += 1;
+    }
+
+    /// Increments `errors` by 1
+    fn inc_errors(&mut self) {
+        self.errors += 1;
     }
 }
 
@@ -68,7 +219,11 @@ impl AddAssign for Stats {
         self.changed += rhs.changed;
         self.not_changed += rhs.not_changed;
         self.skipped += rhs.skipped;
+        for (reason, count) in rhs.skipped_by_reason {
+            *self.skipped_by_reason.entry(reason).or_default() += count;
+        }
         self.errors += rhs.errors;
+        self.files.extend(rhs.files);
     }
 }
 
@@ -76,8 +231,42 @@ impl Display for Stats {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
             f,
-            "Change: {}, Not Changed: {}, Skipped: {}, Errors: {}",
-            self.changed, self.not_changed, self.skipped, self.errors
-        )
+            "Change: {}, Not Changed: {}, Skipped: {}",
+            self.changed, self.not_changed, self.skipped
+        )?;
+        if !self.skipped_by_reason.is_empty() {
+            let reasons: Vec<_> = self
+                .skipped_by_reason
+                .iter()
+                .map(|(reason, count)| format!("{reason}: {count}"))
+                .collect();
+            write!(f, " ({})", reasons.join(", "))?;
+        }
+        write!(f, ", Errors: {}", self.errors)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn add_assign_merges_records() {
+        let mut a = Stats::new();
+        a.add_file(FileRecord::skipped("a.png".into(), SkipReason::NotMarkdown));
+        let mut b = Stats::new();
+        b.add_file(FileRecord::skipped("b.png".into(), SkipReason::NotMarkdown));
+        b.add_file(FileRecord::error(
+            "c.md".into(),
+            &anyhow::anyhow!("Inner").context("Outer"),
+        ));
+        a += b;
+        assert_eq!(a.skipped(), 2);
+        assert_eq!(a.skipped_for(SkipReason::NotMarkdown), 2);
+        assert_eq!(a.errors(), 1);
+        let paths: Vec<_> = a.files().iter().map(|record| record.path.clone()).collect();
+        assert_eq!(paths, ["a.png", "b.png", "c.md"].map(PathBuf::from));
+        assert_eq!(a.files()[2].error_chain, ["Outer", "Inner"]);
+        assert_eq!(a.files()[2].error_kind, Some(ErrorKind::Other));
     }
 }
@@ -1,7 +1,7 @@
 use std::path::Path;
 
-use anyhow::bail;
-use git2::{Repository, Signature};
+use anyhow::{bail, Context};
+use git2::{Repository, Signature, Time};
 
 pub fn init<P: AsRef<Path>>(path: P) -> anyhow::Result<Repository> {
     Ok(git2::Repository::init(path)?)
@@ -14,6 +14,38 @@ pub fn add_all(repo: &Repository, files: &[&str]) -> anyhow::Result<()> {
     Ok(())
 }
 
+/// Moves a file in the working tree and the index (like `git mv`)
+pub fn rename(repo: &Repository, from: &str, to: &str) -> anyhow::Result<()> {
+    let workdir = repo
+        .workdir()
+        .context("Repository has no working directory")?;
+    let to_path = workdir.join(to);
+    if let Some(parent) = to_path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::rename(workdir.join(from), to_path)?;
+    let mut index = repo.index()?;
+    index.remove_path(Path::new(from))?;
+    index.add_path(Path::new(to))?;
+    index.write()?;
+    Ok(())
+}
+
+/// Creates a signature for the test user at noon (UTC) on `date` (Expected format is YYYY-MM-DD)
+pub fn signature_on(date: &str) -> anyhow::Result<Signature<'static>> {
+    let date = chrono::NaiveDate::parse_from_str(date, "%Y-%m-%d")?;
+    let time = date
+        .and_hms_opt(12, 0, 0)
+        .context("Invalid time")?
+        .and_utc()
+        .timestamp();
+    Ok(Signature::new(
+        "test_user",
+        "test_email",
+        &Time::new(time, 0),
+    )?)
+}
+
 /// Commits the files in the index
 ///
 /// Fails if the index is empty
@@ -22,6 +54,24 @@ pub fn commit(repo: &Repository, msg: &str) -> anyhow::Result<()> {
     // Second Source: Needed to see how to find parent https://github.dev/rust-lang/git2-rs/blob/master/examples/add.rs
 
     let sig = Signature::now("test_user", "test_email")?;
+    commit_with_signatures(repo, msg, &sig, &sig)
+}
+
+/// Commits the files in the index on `date` (See [`signature_on`] for format)
+pub fn commit_on(repo: &Repository, msg: &str, date: &str) -> anyhow::Result<()> {
+    let sig = signature_on(date)?;
+    commit_with_signatures(repo, msg, &sig, &sig)
+}
+
+/// Commits the files in the index using the signatures provided
+///
+/// Fails if the index is empty
+pub fn commit_with_signatures(
+    repo: &Repository,
+    msg: &str,
+    author: &Signature,
+    committer: &Signature,
+) -> anyhow::Result<()> {
     let mut index = repo.index()?;
     if index.is_empty() {
         bail!("Empty index found. Unable to commit");
@@ -33,16 +83,40 @@ pub fn commit(repo: &Repository, msg: &str) -> anyhow::Result<()> {
     if let Ok(head) = repo.head() {
         if let Some(parent) = head.target() {
             let parent_commit = repo.find_commit(parent)?;
-            repo.commit(Some("HEAD"), &sig, &sig, msg, &tree, &[&parent_commit])?;
+            repo.commit(
+                Some("HEAD"),
+                author,
+                committer,
+                msg,
+                &tree,
+                &[&parent_commit],
+            )?;
             return Ok(()); // Completed end here
         }
     }
 
     // Assumes there are no commits to use as parent
-    repo.commit(Some("HEAD"), &sig, &sig, msg, &tree, &[])?;
+    repo.commit(Some("HEAD"), author, committer, msg, &tree, &[])?;
     Ok(())
 }
 
 pub fn commit_irrelevant_msg(repo: &Repository) -> anyhow::Result<()> {
     commit(repo, "no msg set")
 }
+
+/// Clones `from` into `to` keeping only the last commit (Uses the git command as git2 cannot create shallow clones)
+pub fn shallow_clone(from: &Path, to: &Path) -> anyhow::Result<()> {
+    let output = std::process::Command::new("git")
+        .arg("clone")
+        .arg("--depth=1")
+        .arg(format!("file://{}", from.display()))
+        .arg(to)
+        .output()?;
+    if !output.status.success() {
+        bail!(
+            "git clone failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+    Ok(())
+}
@@ -1,16 +1,16 @@
-use std::env;
+use anyhow::Context;
 use std::fmt::Debug;
 use std::fs;
 use std::fs::File;
 use std::io::Write;
 use std::path::Path;
 use std::path::PathBuf;
-use std::sync::OnceLock;
 use strum::EnumIter;
 use zola_chrono::run;
 use zola_chrono::Cli;
+use zola_chrono::Stats;
 
-mod git_commands;
+pub mod git_commands;
 
 pub enum ResultExpected {
     IsOk,
@@ -41,7 +41,8 @@ pub enum TestDir {
 impl TestDir {
     pub(crate) const TEST_DIR_BASE: &'static str = "tests/test_folders/";
     pub(crate) fn to_path(&self) -> PathBuf {
-        let base_test_folder = PathBuf::from(Self::TEST_DIR_BASE);
+        // Absolute because `run` changes the current working directory and tests run in parallel
+        let base_test_folder = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join(Self::TEST_DIR_BASE);
         let sub_folder = match self {
             TestDir::NoVCS => "no_vcs",
             TestDir::Clean => "clean",
@@ -53,11 +54,7 @@ impl TestDir {
     }
 
     pub(crate) fn to_canonicalized_path(&self) -> PathBuf {
-        // Need to capture and lock in value of original directory because `run` changes the current working directory if it runs successfully and following tests fail
-        static ORG_WORKING_DIR: OnceLock<PathBuf> = OnceLock::new();
-        let org_dir =
-            ORG_WORKING_DIR.get_or_init(|| env::current_dir().expect("Failed to get current_dir"));
-        let result = org_dir.join(self.to_path());
+        let result = self.to_path();
         assert!(result.exists(), "Path not found: {result:?}");
         result.canonicalize().unwrap()
     }
@@ -107,6 +104,101 @@ pub fn create_test_folder(test_dir: &TestDir) -> anyhow::Result<()> {
     Ok(())
 }
 
+/// Creates an empty repository for tests that need a specific history
+///
+/// Unlike [`create_test_folder`] any folder left over from a previous run is removed first as these tests may modify files.
+pub fn create_fresh_repo(name: &str) -> anyhow::Result<(PathBuf, git2::Repository)> {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join(TestDir::TEST_DIR_BASE)
+        .join(name);
+    if path.exists() {
+        fs::remove_dir_all(&path)?;
+    }
+    cargo_util::paths::create_dir_all(&path)?;
+    let repo = git_commands::init(&path)?;
+    Ok((path.canonicalize()?, repo))
+}
+
+/// Writes a page with the front matter provided (without delimiters) and some content
+pub fn write_page<P: AsRef<Path>>(
+    path: P,
+    front_matter: &str,
+    content: &str,
+) -> anyhow::Result<()> {
+    let path = path.as_ref();
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, format!("+++\n{front_matter}\n+++\n\n{content}\n"))?;
+    Ok(())
+}
+
+/// A page to write as its path (relative to the repository), front matter (without delimiters) and content
+pub type Page<'a> = (&'a str, &'a str, &'a str);
+
+/// Writes `pages` in the working tree of `repo` and stages all the changes
+pub fn stage_pages(repo: &git2::Repository, pages: &[Page]) -> anyhow::Result<()> {
+    let workdir = repo
+        .workdir()
+        .context("Repository has no working directory")?;
+    for (name, front_matter, content) in pages {
+        write_page(workdir.join(name), front_matter, content)?;
+    }
+    git_commands::add_all(repo, &["*"])
+}
+
+/// Writes `pages` in the working tree of `repo` then commits all the changes on `date` (See [`git_commands::commit_on`])
+pub fn commit_pages(
+    repo: &git2::Repository,
+    pages: &[Page],
+    msg: &str,
+    date: &str,
+) -> anyhow::Result<()> {
+    stage_pages(repo, pages)?;
+    git_commands::commit_on(repo, msg, date)
+}
+
+/// Runs unattended on `path` using the other options in `cli`
+pub fn run_unattended(path: &Path, cli: Cli) -> anyhow::Result<Stats> {
+    let cli = Cli {
+        root_path: path.to_string_lossy().to_string(),
+        unattended: true,
+        ..cli
+    };
+    Ok(run(&cli)?)
+}
+
+/// Returns the first line of the file at `path` that starts with `prefix` (eg. the `date` of a page)
+pub fn line_starting_with(path: &Path, prefix: &str) -> anyhow::Result<Option<String>> {
+    let content = fs::read_to_string(path)?;
+    Ok(content
+        .lines()
+        .find(|line| line.starts_with(prefix))
+        .map(String::from))
+}
+
+/// Asserts that the file at `path` has the line `expected` (eg. `date = 2020-01-01`)
+pub fn assert_has_line(path: &Path, expected: &str) {
+    let actual = fs::read_to_string(path).expect("Failed to read file");
+    assert!(
+        actual.lines().any(|line| line == expected),
+        "Expected {expected:?} in {path:?}:\n{actual}"
+    );
+}
+
+/// Runs in check mode (unattended) on `path` using the other options in `cli` and returns the number of files that would have been changed
+pub fn changed_count_in_check_mode(path: &Path, cli: Cli) -> u64 {
+    let cli = Cli {
+        root_path: path.to_string_lossy().to_string(),
+        unattended: true,
+        should_check_only: true,
+        ..cli
+    };
+    let stats = run(&cli).expect("Run failed");
+    assert_eq!(stats.errors(), 0, "{stats}");
+    stats.changed()
+}
+
 fn create_abc<P: AsRef<Path>>(path: P) -> anyhow::Result<()> {
     let path = path.as_ref();
     for name in ["a", "b", "c"] {
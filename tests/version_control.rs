@@ -1,13 +1,17 @@
 use anyhow::Context;
 use rstest::{fixture, rstest};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use strum::IntoEnumIterator;
 use utils::{
-    create_test_folder,
+    assert_has_line, changed_count_in_check_mode, commit_pages, create_fresh_repo,
+    create_test_folder, git_commands, line_starting_with, run_unattended, stage_pages, write_page,
     ResultExpected::{self, IsErr, IsOk},
     TestDir as TD,
 };
-use zola_chrono::{run, Cli};
+use zola_chrono::{
+    explain, run, Cli, DateSource, DraftPolicy, Error, ErrorKind, FileStatus, FilenameDateMode,
+    FrontMatterPart, Precision, SectionMode, SkipReason,
+};
 
 mod utils;
 
@@ -22,7 +26,7 @@ fn non_existent_folder() {
 
     // Ensure run fails if folder doesn't exist
     let actual = run(&cli);
-    assert!(actual.is_err());
+    assert_eq!(actual.unwrap_err().kind(), ErrorKind::Io);
 }
 
 #[fixture]
@@ -73,6 +77,1092 @@ fn test_with_unattended(
         unattended: true,
         should_check_only,
         allow_dirty,
+        ..Default::default()
     };
     utils::test_run(cli, test_dir, expected);
 }
+
+#[rstest]
+#[case(false, false, 0)]
+#[case(false, true, 1)]
+#[case(true, false, 1)]
+#[case(true, true, 1)]
+fn rename_keeps_history(
+    #[case] is_content_changed: bool,
+    #[case] count_moves: bool,
+    #[case] expected_changed: u64,
+) -> anyhow::Result<()> {
+    let (path, repo) = create_fresh_repo(&format!(
+        "rename_content_changed_{is_content_changed}_count_moves_{count_moves}"
+    ))?;
+    let front_matter = "date = 2020-01-01\nupdated = 2021-06-01";
+    let content = "A line that stays the same\n".repeat(20);
+    commit_pages(
+        &repo,
+        &[("blog/foo.md", front_matter, &content)],
+        "add page",
+        "2021-06-01",
+    )?;
+
+    git_commands::rename(&repo, "blog/foo.md", "blog/2023/foo.md")?;
+    if is_content_changed {
+        let content = format!("{content}One new line");
+        stage_pages(&repo, &[("blog/2023/foo.md", front_matter, &content)])?;
+    }
+    git_commands::commit_on(&repo, "move page", "2023-01-01")?;
+
+    // By default only an edit to the content after `updated` requires a change, with `--count-moves` the move does too
+    let cli = Cli {
+        count_moves,
+        ..Default::default()
+    };
+    assert_eq!(changed_count_in_check_mode(&path, cli), expected_changed);
+    Ok(())
+}
+
+#[rstest]
+#[case(false, None, 1)]
+#[case(false, Some(FrontMatterPart::Dates), 0)]
+#[case(false, Some(FrontMatterPart::FrontMatter), 0)]
+#[case(true, None, 1)]
+#[case(true, Some(FrontMatterPart::Dates), 1)]
+#[case(true, Some(FrontMatterPart::FrontMatter), 0)]
+fn skip_front_matter_only_commits(
+    #[case] is_title_changed: bool,
+    #[case] skip_commits_changing_only: Option<FrontMatterPart>,
+    #[case] expected_changed: u64,
+) -> anyhow::Result<()> {
+    let (path, repo) = create_fresh_repo(&format!(
+        "skip_front_matter_only_commits_{is_title_changed}_{skip_commits_changing_only:?}"
+    ))?;
+    let content = "Some content\n".repeat(5);
+    commit_pages(
+        &repo,
+        &[("page.md", "date = 2020-01-01", &content)],
+        "add page",
+        "2020-01-01",
+    )?;
+
+    // Simulates committing the output of a previous run a day after it was run
+    let title = if is_title_changed {
+        "title = \"New\"\n"
+    } else {
+        ""
+    };
+    let front_matter = format!("{title}date = 2020-01-01\nupdated = 2022-01-01");
+    commit_pages(
+        &repo,
+        &[("page.md", &front_matter, &content)],
+        "update front matter",
+        "2022-01-02",
+    )?;
+
+    let cli = Cli {
+        skip_commits_changing_only,
+        ..Default::default()
+    };
+    assert_eq!(changed_count_in_check_mode(&path, cli), expected_changed);
+    Ok(())
+}
+
+#[rstest]
+#[case(FrontMatterPart::Dates)]
+#[case(FrontMatterPart::FrontMatter)]
+fn skip_front_matter_only_commits_checks_body(
+    #[case] skip_commits_changing_only: FrontMatterPart,
+) -> anyhow::Result<()> {
+    let (path, repo) = create_fresh_repo(&format!(
+        "skip_front_matter_only_commits_checks_body_{skip_commits_changing_only:?}"
+    ))?;
+    // Text that is not valid UTF-8 in a file that is not a page
+    std::fs::write(path.join("notes.txt"), b"caf\xe9\n")?;
+    commit_pages(
+        &repo,
+        &[("page.md", "date = 2020-01-01", "Notes\ndate: TBD\n")],
+        "add page",
+        "2020-01-01",
+    )?;
+
+    // A body line that looks like it sets a date is still an edit to the content
+    std::fs::write(path.join("notes.txt"), b"caf\xe9 cr\xe8me\n")?;
+    commit_pages(
+        &repo,
+        &[("page.md", "date = 2020-01-01", "Notes\ndate: 2022-01-02\n")],
+        "set date in body",
+        "2022-01-02",
+    )?;
+
+    let cli = Cli {
+        skip_commits_changing_only: Some(skip_commits_changing_only),
+        ..Default::default()
+    };
+    assert_eq!(changed_count_in_check_mode(&path, cli), 1);
+    Ok(())
+}
+
+#[rstest]
+#[case("Add details", vec![], 1)]
+#[case("Fix typo", vec![], 1)]
+#[case("Fix typo", vec!["(?i)typo".to_string()], 0)]
+#[case("Reformat\n\nChrono-Skip: true", vec![], 0)]
+fn skip_commits_by_message(
+    #[case] message: &str,
+    #[case] skip_commit_pattern: Vec<String>,
+    #[case] expected_changed: u64,
+) -> anyhow::Result<()> {
+    let (path, repo) = create_fresh_repo(&format!(
+        "skip_commits_by_message_{}_{}",
+        message.split_whitespace().next().unwrap_or_default(),
+        skip_commit_pattern.len()
+    ))?;
+    commit_pages(
+        &repo,
+        &[("page.md", "date = 2020-01-01", "Some content\n")],
+        "add page",
+        "2020-01-01",
+    )?;
+    commit_pages(
+        &repo,
+        &[("page.md", "date = 2020-01-01", "Some other content\n")],
+        message,
+        "2021-01-01",
+    )?;
+
+    let cli = Cli {
+        skip_commit_pattern,
+        ..Default::default()
+    };
+    assert_eq!(changed_count_in_check_mode(&path, cli), expected_changed);
+    Ok(())
+}
+
+#[rstest]
+#[case(None, 1)]
+#[case(Some(DateSource::Committer), 1)]
+#[case(Some(DateSource::Author), 0)]
+fn date_source_of_commits(
+    #[case] date_source: Option<DateSource>,
+    #[case] expected_changed: u64,
+) -> anyhow::Result<()> {
+    let (path, repo) = create_fresh_repo(&format!("date_source_of_commits_{date_source:?}"))?;
+    stage_pages(&repo, &[("page.md", "date = 2020-06-01", "Some content\n")])?;
+
+    // Simulates a commit that was rebased after it was authored
+    let author = git_commands::signature_on("2020-06-01")?;
+    let committer = git_commands::signature_on("2021-01-01")?;
+    git_commands::commit_with_signatures(&repo, "add page", &author, &committer)?;
+
+    let cli = Cli {
+        date_source,
+        ..Default::default()
+    };
+    assert_eq!(changed_count_in_check_mode(&path, cli), expected_changed);
+    Ok(())
+}
+
+#[rstest]
+#[case(false, "date = 2021-01-01")]
+#[case(true, "date = 2020-01-01")]
+fn backfill_date_from_first_commit(
+    #[case] date_from_first_commit: bool,
+    #[case] expected_date_line: &str,
+) -> anyhow::Result<()> {
+    let (path, repo) =
+        create_fresh_repo(&format!("date_from_first_commit_{date_from_first_commit}"))?;
+    let content = "Some content\n".repeat(5);
+    commit_pages(
+        &repo,
+        &[("page.md", "title = \"Page\"", &content)],
+        "add page",
+        "2020-01-01",
+    )?;
+    git_commands::rename(&repo, "page.md", "blog/page.md")?;
+    git_commands::commit_on(&repo, "move page", "2020-06-01")?;
+    commit_pages(
+        &repo,
+        &[("blog/page.md", "title = \"Page\"", "New content\n")],
+        "edit page",
+        "2021-01-01",
+    )?;
+
+    let cli = Cli {
+        date_from_first_commit,
+        ..Default::default()
+    };
+    let stats = run_unattended(&path, cli)?;
+    assert_eq!(stats.changed(), 1);
+    assert_has_line(&path.join("blog/page.md"), expected_date_line);
+    Ok(())
+}
+
+#[test]
+fn shallow_clone_ignores_first_commit() -> anyhow::Result<()> {
+    let (path, repo) = create_fresh_repo("shallow_clone_ignores_first_commit")?;
+    commit_pages(
+        &repo,
+        &[("page.md", "title = \"Page\"", "Some content\n")],
+        "add page",
+        "2020-01-01",
+    )?;
+    commit_pages(
+        &repo,
+        &[("page.md", "title = \"Page\"", "New content\n")],
+        "edit page",
+        "2021-01-01",
+    )?;
+    let clone_path = path.with_file_name("shallow_clone_ignores_first_commit_clone");
+    if clone_path.exists() {
+        std::fs::remove_dir_all(&clone_path)?;
+    }
+    git_commands::shallow_clone(&path, &clone_path)?;
+
+    let cli = Cli {
+        date_from_first_commit: true,
+        today: Some("2022-03-04".parse()?),
+        ..Default::default()
+    };
+    let fallback_date = |repo_path: &Path| -> anyhow::Result<Option<String>> {
+        let explanation = explain(&cli, &repo_path.join("page.md").to_string_lossy())?;
+        Ok(explanation.rules.and_then(|rules| rules.fallback_date))
+    };
+    assert_eq!(fallback_date(&path)?.as_deref(), Some("2020-01-01"));
+    // The only commit in the clone shows the page as added on the day it was edited
+    assert_eq!(fallback_date(&clone_path)?, None);
+    Ok(())
+}
+
+#[rstest]
+#[case("2022-03-04", 0, "updated = 2022-03-04")]
+#[case("2020-12-31", 1, "date = 2020-01-01")] // Last edit would be in the future
+fn today_from_cli(
+    #[case] today: &str,
+    #[case] expected_errors: u64,
+    #[case] expected_line: &str,
+) -> anyhow::Result<()> {
+    let (path, repo) = create_fresh_repo(&format!("today_from_cli_{today}"))?;
+    commit_pages(
+        &repo,
+        &[("page.md", "date = 2020-01-01", "Some content\n")],
+        "add page",
+        "2020-01-01",
+    )?;
+    commit_pages(
+        &repo,
+        &[("page.md", "date = 2020-01-01", "New content\n")],
+        "edit page",
+        "2021-01-01",
+    )?;
+
+    let cli = Cli {
+        today: Some(today.parse()?),
+        ..Default::default()
+    };
+    let stats = run_unattended(&path, cli)?;
+    assert_eq!(stats.errors(), expected_errors);
+    assert_has_line(&path.join("page.md"), expected_line);
+    Ok(())
+}
+
+#[rstest]
+#[case(None, 0)]
+#[case(Some(DateSource::Committer), 1)]
+fn date_source_from_config_file(
+    #[case] date_source: Option<DateSource>,
+    #[case] expected_changed: u64,
+) -> anyhow::Result<()> {
+    let (path, repo) = create_fresh_repo(&format!("date_source_from_config_file_{date_source:?}"))?;
+    std::fs::write(
+        path.join("config.toml"),
+        "base_url = \"https://example.com\"\n\n[extra.zola_chrono]\ndate_source = \"author\"\n",
+    )?;
+    stage_pages(
+        &repo,
+        &[("content/page.md", "date = 2020-06-01", "Some content\n")],
+    )?;
+    let author = git_commands::signature_on("2020-06-01")?;
+    let committer = git_commands::signature_on("2021-01-01")?;
+    git_commands::commit_with_signatures(&repo, "add page", &author, &committer)?;
+
+    // Setting from the command line takes precedence over the config file
+    let cli = Cli {
+        date_source,
+        ..Default::default()
+    };
+    assert_eq!(
+        changed_count_in_check_mode(&path.join("content"), cli),
+        expected_changed
+    );
+    Ok(())
+}
+
+#[test]
+fn zola_site_root_processes_only_content() -> anyhow::Result<()> {
+    let (path, repo) = create_fresh_repo("zola_site_root_processes_only_content")?;
+    std::fs::write(
+        path.join("config.toml"),
+        "base_url = \"https://example.com\"\ndefault_language = \"en\"\n\n[languages.fr]\n",
+    )?;
+    stage_pages(
+        &repo,
+        &[
+            ("content/page.md", "title = \"Page\"", ""),
+            ("content/page.fr.md", "title = \"Page\"", ""),
+            ("content/_index.fr.md", "title = \"Section\"", ""),
+            ("templates/notes.md", "title = \"Notes\"", ""),
+        ],
+    )?;
+    git_commands::commit(&repo, "add site")?;
+
+    let cli = Cli {
+        should_check_only: true,
+        ..Default::default()
+    };
+    let stats = run_unattended(&path, cli)?;
+    assert_eq!(stats.errors(), 0, "{stats}");
+    assert_eq!(stats.changed(), 2, "{stats}");
+    assert_eq!(stats.skipped(), 1, "{stats}");
+    Ok(())
+}
+
+#[rstest]
+#[case(None, None)]
+#[case(Some(SectionMode::Own), Some("date = 2020-01-01"))]
+#[case(Some(SectionMode::Children), Some("date = 2021-01-01"))]
+fn section_files_by_mode(
+    #[case] sections: Option<SectionMode>,
+    #[case] expected_date_line: Option<&str>,
+) -> anyhow::Result<()> {
+    let (path, repo) = create_fresh_repo(&format!("section_files_by_mode_{sections:?}"))?;
+    commit_pages(
+        &repo,
+        &[
+            ("blog/_index.md", "title = \"Blog\"", ""),
+            ("blog/a.md", "date = 2020-01-01", "Some content\n"),
+        ],
+        "add blog",
+        "2020-01-01",
+    )?;
+    commit_pages(
+        &repo,
+        &[("blog/a.md", "date = 2020-01-01", "New content\n")],
+        "edit page",
+        "2021-01-01",
+    )?;
+    // Pages of nested sections are not children of the section
+    commit_pages(
+        &repo,
+        &[
+            ("blog/news/_index.md", "title = \"News\"", ""),
+            ("blog/news/b.md", "date = 2022-01-01", "Some content\n"),
+        ],
+        "add news",
+        "2022-01-01",
+    )?;
+
+    let cli = Cli {
+        sections,
+        ..Default::default()
+    };
+    let stats = run_unattended(&path, cli)?;
+    assert_eq!(stats.errors(), 0, "{stats}");
+    assert_eq!(
+        stats.skipped_for(SkipReason::SectionFile),
+        if sections.is_none() { 2 } else { 0 },
+        "{stats}"
+    );
+    assert_eq!(
+        line_starting_with(&path.join("blog/_index.md"), "date")?.as_deref(),
+        expected_date_line
+    );
+    Ok(())
+}
+
+#[rstest]
+#[case(None, Some("date = 2020-01-01"), "date = 2020-01-01")]
+#[case(
+    Some(DraftPolicy::Skip),
+    Some("date = 2020-01-01"),
+    "date = 2020-01-01"
+)]
+#[case(Some(DraftPolicy::Defer), None, "date = 2020-01-01")]
+fn draft_policies(
+    #[case] drafts: Option<DraftPolicy>,
+    #[case] expected_draft_date: Option<&str>,
+    #[case] expected_published_date: &str,
+) -> anyhow::Result<()> {
+    let (path, repo) = create_fresh_repo(&format!("draft_policies_{drafts:?}"))?;
+    commit_pages(
+        &repo,
+        &[
+            (
+                "draft.md",
+                "draft = true\ndate = 2020-01-01",
+                "Some content\n",
+            ),
+            ("published.md", "title = \"Published\"", ""),
+        ],
+        "add pages",
+        "2020-01-01",
+    )?;
+
+    let cli = Cli {
+        today: Some("2022-03-04".parse()?),
+        drafts,
+        ..Default::default()
+    };
+    let stats = run_unattended(&path, cli)?;
+    assert_eq!(stats.errors(), 0, "{stats}");
+    assert_eq!(
+        stats.skipped_for(SkipReason::Draft),
+        u64::from(drafts == Some(DraftPolicy::Skip)),
+        "{stats}"
+    );
+    assert_eq!(
+        line_starting_with(&path.join("draft.md"), "date")?.as_deref(),
+        expected_draft_date,
+        "draft.md"
+    );
+    assert_has_line(&path.join("published.md"), expected_published_date);
+    Ok(())
+}
+
+#[rstest]
+#[case("date = 2020-01-01", "updated = 2021-01-01T12:00:00Z")]
+#[case("title = \"No date\"", "date = 2021-01-01T12:00:00Z")]
+fn committed_output_is_stable_with_time(
+    #[case] front_matter: &str,
+    #[case] expected_line: &str,
+) -> anyhow::Result<()> {
+    let (path, repo) = create_fresh_repo(&format!(
+        "committed_output_is_stable_with_time_{}",
+        front_matter.len()
+    ))?;
+    commit_pages(
+        &repo,
+        &[("page.md", front_matter, "Some content\n")],
+        "add page",
+        "2021-01-01",
+    )?;
+
+    let cli = Cli {
+        today: Some("2022-03-04".parse()?),
+        precision: Some(Precision::OffsetDatetime),
+        timezone: Some("UTC".parse().map_err(anyhow::Error::msg)?),
+        ..Default::default()
+    };
+    let stats = run_unattended(&path, cli.clone())?;
+    assert_eq!(stats.changed(), 1, "{stats}");
+    assert_has_line(&path.join("page.md"), expected_line);
+    git_commands::add_all(&repo, &["*"])?;
+    git_commands::commit_on(&repo, "set dates", "2022-03-04")?;
+
+    // The commit of the tool's own changes must not need another change
+    assert_eq!(changed_count_in_check_mode(&path, cli), 0);
+    Ok(())
+}
+
+#[test]
+fn deferred_drafts_published_today() -> anyhow::Result<()> {
+    let (path, repo) = create_fresh_repo("deferred_drafts_published_today")?;
+    commit_pages(
+        &repo,
+        &[
+            ("was_draft.md", "draft = true", "Some content\n"),
+            ("published.md", "title = \"Published\"", ""),
+        ],
+        "add pages",
+        "2020-01-01",
+    )?;
+    stage_pages(
+        &repo,
+        &[
+            ("was_draft.md", "draft = false", "Some content\n"),
+            ("new.md", "title = \"New\"", ""),
+        ],
+    )?;
+
+    let cli = Cli {
+        today: Some("2022-03-04".parse()?),
+        drafts: Some(DraftPolicy::Defer),
+        ..Default::default()
+    };
+    let stats = run_unattended(&path, cli)?;
+    assert_eq!(stats.errors(), 0, "{stats}");
+    assert_has_line(&path.join("was_draft.md"), "date = 2022-03-04");
+    assert_has_line(&path.join("new.md"), "date = 2022-03-04");
+    assert_has_line(&path.join("published.md"), "date = 2020-01-01");
+    Ok(())
+}
+
+#[rstest]
+#[case(
+    Some(FilenameDateMode::Ignore),
+    "date = 2021-01-01",
+    "date = 2022-02-02"
+)]
+#[case(
+    Some(FilenameDateMode::Report),
+    "date = 2020-05-06",
+    "date = 2022-02-02"
+)]
+#[case(None, "date = 2020-05-06", "date = 2020-07-08")]
+#[case(Some(FilenameDateMode::Fix), "date = 2020-05-06", "date = 2020-07-08")]
+fn dates_from_file_names(
+    #[case] filename_date: Option<FilenameDateMode>,
+    #[case] expected_post_date: &str,
+    #[case] expected_bundle_date: &str,
+) -> anyhow::Result<()> {
+    let (path, repo) = create_fresh_repo(&format!("dates_from_file_names_{filename_date:?}"))?;
+    commit_pages(
+        &repo,
+        &[
+            ("2020-05-06-post.md", "title = \"Post\"", ""),
+            ("2020-07-08_bundle/index.md", "date = 2022-02-02", ""),
+        ],
+        "add pages",
+        "2021-01-01",
+    )?;
+
+    let cli = Cli {
+        today: Some("2022-03-04".parse()?),
+        filename_date,
+        ..Default::default()
+    };
+    let stats = run_unattended(&path, cli)?;
+    assert_eq!(stats.errors(), 0, "{stats}");
+    assert_has_line(&path.join("2020-05-06-post.md"), expected_post_date);
+    assert_has_line(
+        &path.join("2020-07-08_bundle/index.md"),
+        expected_bundle_date,
+    );
+    Ok(())
+}
+
+#[rstest]
+#[case(false, None)]
+#[case(true, Some("updated = 2022-03-04"))]
+fn bundle_asset_changes(
+    #[case] bundle_assets: bool,
+    #[case] expected_updated_line: Option<&str>,
+) -> anyhow::Result<()> {
+    let (path, repo) = create_fresh_repo(&format!("bundle_asset_changes_{bundle_assets}"))?;
+    std::fs::create_dir_all(path.join("post"))?;
+    std::fs::write(path.join("post/diagram.svg"), "<svg></svg>")?;
+    commit_pages(
+        &repo,
+        &[
+            ("post/index.md", "date = 2020-01-01", "![](diagram.svg)\n"),
+            (
+                "post/index.fr.md",
+                "date = 2020-01-01",
+                "![](diagram.svg)\n",
+            ),
+        ],
+        "add post",
+        "2020-01-01",
+    )?;
+    std::fs::write(path.join("post/diagram.svg"), "<svg><rect/></svg>")?;
+    git_commands::add_all(&repo, &["*"])?;
+    git_commands::commit_on(&repo, "replace diagram", "2021-01-01")?;
+
+    let cli = Cli {
+        today: Some("2022-03-04".parse()?),
+        bundle_assets,
+        ..Default::default()
+    };
+    let stats = run_unattended(&path, cli)?;
+    assert_eq!(stats.errors(), 0, "{stats}");
+    for name in ["post/index.md", "post/index.fr.md"] {
+        assert_eq!(
+            line_starting_with(&path.join(name), "updated")?.as_deref(),
+            expected_updated_line,
+            "{name}"
+        );
+    }
+    Ok(())
+}
+
+#[test]
+fn per_file_report() -> anyhow::Result<()> {
+    let (path, repo) = create_fresh_repo("per_file_report")?;
+    std::fs::write(path.join("c.md"), "No front matter")?;
+    std::fs::write(path.join("image.png"), "")?;
+    commit_pages(
+        &repo,
+        &[
+            ("a.md", "date = 2020-01-01", "Some content\n"),
+            ("b.md", "date = 2020-01-01", ""),
+        ],
+        "add pages",
+        "2020-01-01",
+    )?;
+    commit_pages(
+        &repo,
+        &[("a.md", "date = 2020-01-01", "New content\n")],
+        "edit a",
+        "2021-01-01",
+    )?;
+
+    let cli = Cli {
+        should_check_only: true,
+        today: Some("2022-03-04".parse()?),
+        ..Default::default()
+    };
+    let stats = run_unattended(&path, cli)?;
+    let record = |name: &str| {
+        stats
+            .files()
+            .iter()
+            .find(|record| record.path.ends_with(name))
+            .unwrap_or_else(|| panic!("no record for {name}"))
+    };
+    let a = record("a.md");
+    assert_eq!(a.status, FileStatus::Changed);
+    assert_eq!(a.old_date.as_deref(), Some("2020-01-01"));
+    assert_eq!(a.old_updated, None);
+    assert_eq!(a.new_updated.as_deref(), Some("2022-03-04"));
+    assert_eq!(record("b.md").status, FileStatus::NotChanged);
+    let c = record("c.md");
+    assert_eq!(c.status, FileStatus::Error);
+    assert!(c.error_chain[0].starts_with("Processing failed for"));
+    assert_eq!(c.error_kind, Some(ErrorKind::MissingFrontMatter));
+    assert!(c
+        .error_chain
+        .last()
+        .unwrap()
+        .starts_with("Failed to find front matter"));
+    assert_eq!(
+        record("image.png").skip_reason,
+        Some(SkipReason::NotMarkdown)
+    );
+
+    assert_eq!(a.diff, None);
+
+    let json = serde_json::to_value(&stats)?;
+    assert_eq!(json["changed"], 1);
+    assert_eq!(json["skipped_by_reason"]["not_markdown"], 1);
+    assert_eq!(json["files"].as_array().map(Vec::len), Some(4));
+    Ok(())
+}
+
+#[test]
+fn typed_errors() -> anyhow::Result<()> {
+    let (path, repo) = create_fresh_repo("typed_errors")?;
+    commit_pages(
+        &repo,
+        &[
+            ("bad_toml.md", "date = ", ""),
+            ("future.md", "date = 2020-01-01", ""),
+        ],
+        "add pages",
+        "2023-01-01",
+    )?;
+
+    let cli = Cli {
+        root_path: path.to_string_lossy().to_string(),
+        unattended: true,
+        today: Some("2022-03-04".parse()?),
+        ..Default::default()
+    };
+    let stats = run(&cli)?;
+    let kind = |name: &str| {
+        stats
+            .files()
+            .iter()
+            .find(|record| record.path.ends_with(name))
+            .and_then(|record| record.error_kind)
+    };
+    assert_eq!(kind("bad_toml.md"), Some(ErrorKind::InvalidToml));
+    assert_eq!(kind("future.md"), Some(ErrorKind::FutureCommitDate));
+
+    std::fs::write(path.join("dirty.md"), "Not committed")?;
+    let Err(Error::UncleanVcs {
+        path: error_path, ..
+    }) = run(&cli)
+    else {
+        panic!("expected an unclean version control error");
+    };
+    assert_eq!(error_path, path.canonicalize()?);
+    Ok(())
+}
+
+#[test]
+fn staged_files_only() -> anyhow::Result<()> {
+    let (path, repo) = create_fresh_repo("staged_files_only")?;
+    let page = |name| (name, "date = 2020-01-01", "Some content\n");
+    commit_pages(
+        &repo,
+        &[page("a.md"), page("b.md"), page("c.md")],
+        "add pages",
+        "2020-01-01",
+    )?;
+    std::fs::write(path.join("image.png"), "")?;
+    stage_pages(
+        &repo,
+        &[
+            ("a.md", "date = 2020-01-01", "Staged edit\n"),
+            ("c.md", "date = 2020-01-01", "Staged edit\n"),
+        ],
+    )?;
+    write_page(path.join("c.md"), "date = 2020-01-01", "Edit not staged\n")?;
+    let partially_staged = std::fs::read_to_string(path.join("c.md"))?;
+
+    let cli = Cli {
+        staged: true,
+        today: Some("2022-03-04".parse()?),
+        ..Default::default()
+    };
+    let stats = run_unattended(&path, cli)?;
+    let names: Vec<_> = stats
+        .files()
+        .iter()
+        .map(|record| record.path.file_name().unwrap().to_string_lossy())
+        .collect();
+    assert_eq!(names, ["a.md", "c.md"]);
+    assert_eq!(stats.files()[0].status, FileStatus::Changed);
+    assert_eq!(stats.files()[0].new_updated.as_deref(), Some("2022-03-04"));
+    assert_eq!(stats.files()[1].status, FileStatus::Error);
+    assert_eq!(
+        std::fs::read_to_string(path.join("c.md"))?,
+        partially_staged
+    );
+
+    // The change made to the page is staged along with the edit
+    let mut index = repo.index()?;
+    index.read(true)?;
+    let entry = index
+        .get_path(std::path::Path::new("a.md"), 0)
+        .context("a.md not in index")?;
+    let staged = repo.find_blob(entry.id)?;
+    assert_eq!(
+        std::str::from_utf8(staged.content())?,
+        std::fs::read_to_string(path.join("a.md"))?
+    );
+    assert!(std::str::from_utf8(staged.content())?.contains("updated = 2022-03-04"));
+    Ok(())
+}
+
+#[test]
+fn staged_history_sources() -> anyhow::Result<()> {
+    let (path, repo) = create_fresh_repo("staged_history_sources")?;
+    std::fs::create_dir_all(path.join("blog/post"))?;
+    std::fs::write(path.join("blog/post/diagram.svg"), "<svg></svg>")?;
+    let page = |name| (name, "date = 2020-01-01", "Some content\n");
+    commit_pages(
+        &repo,
+        &[
+            page("blog/_index.md"),
+            page("blog/a.md"),
+            page("blog/post/index.md"),
+            page("b.md"),
+        ],
+        "add pages",
+        "2020-01-01",
+    )?;
+    std::fs::write(path.join("blog/post/diagram.svg"), "<svg><rect/></svg>")?;
+    stage_pages(
+        &repo,
+        &[("blog/a.md", "date = 2020-01-01", "Staged edit\n")],
+    )?;
+
+    let cli = Cli {
+        staged: true,
+        sections: Some(SectionMode::Children),
+        bundle_assets: true,
+        today: Some("2022-03-04".parse()?),
+        ..Default::default()
+    };
+    let stats = run_unattended(&path, cli)?;
+    // The section's child page and the bundle's asset are staged
+    let changed: Vec<_> = stats
+        .files()
+        .iter()
+        .filter(|record| record.status == FileStatus::Changed)
+        .map(|record| record.path.strip_prefix(&path).unwrap().to_path_buf())
+        .collect();
+    assert_eq!(
+        changed,
+        [
+            PathBuf::from("blog/_index.md"),
+            PathBuf::from("blog/a.md"),
+            PathBuf::from("blog/post/index.md")
+        ],
+        "{stats}"
+    );
+    let mut index = repo.index()?;
+    index.read(true)?;
+    for name in changed {
+        let entry = index.get_path(&name, 0).context("page not in index")?;
+        let staged = repo.find_blob(entry.id)?;
+        assert!(
+            std::str::from_utf8(staged.content())?.contains("updated = 2022-03-04"),
+            "{name:?} not staged"
+        );
+    }
+    Ok(())
+}
+
+#[test]
+fn diff_of_changes() -> anyhow::Result<()> {
+    let (path, repo) = create_fresh_repo("diff_of_changes")?;
+    commit_pages(
+        &repo,
+        &[("a.md", "date = 2020-01-01", "Some content\n")],
+        "add page",
+        "2020-01-01",
+    )?;
+    commit_pages(
+        &repo,
+        &[("a.md", "date = 2020-01-01", "New content\n")],
+        "edit page",
+        "2021-01-01",
+    )?;
+
+    let cli = Cli {
+        should_check_only: true,
+        today: Some("2022-03-04".parse()?),
+        diff: true,
+        ..Default::default()
+    };
+    let stats = run_unattended(&path, cli)?;
+    let diff = stats.files()[0].diff.as_deref().unwrap_or_default();
+    assert!(
+        diff.lines().any(|line| line == "+updated = 2022-03-04"),
+        "{diff}"
+    );
+    assert_eq!(diff.lines().filter(|line| line.starts_with('-')).count(), 1); // Only the header
+    Ok(())
+}
+
+#[test]
+fn explain_page() -> anyhow::Result<()> {
+    let (path, repo) = create_fresh_repo("explain_page")?;
+    let front_matter = "date = 2020-01-01\nupdated = \"soon\"";
+    commit_pages(
+        &repo,
+        &[("a.md", front_matter, "Some content\n")],
+        "add page",
+        "2020-01-01",
+    )?;
+    commit_pages(
+        &repo,
+        &[("a.md", front_matter, "New content\n")],
+        "edit page",
+        "2021-01-01",
+    )?;
+    let head = repo.head()?.peel_to_commit()?.id().to_string();
+
+    let cli = Cli {
+        today: Some("2022-03-04".parse()?),
+        ..Default::default()
+    };
+    let page = path.join("a.md");
+    let before = std::fs::read_to_string(&page)?;
+    let explanation = explain(&cli, &page.to_string_lossy())?;
+    assert_eq!(
+        std::fs::read_to_string(&page)?,
+        before,
+        "Page must not change"
+    );
+    assert_eq!(explanation.last_commit, Some(head));
+    let rules = explanation.rules.expect("rules should be applied");
+    assert_eq!(rules.org_updated.as_deref(), Some("\"soon\""));
+    assert_eq!(rules.fixups, ["Non date value found for `updated`"]);
+    assert_eq!(
+        rules.rule,
+        "`updated` is not a date: `updated` set to today"
+    );
+    assert_eq!(rules.new_updated.as_deref(), Some("2022-03-04"));
+    Ok(())
+}
+
+#[test]
+fn include_and_exclude_patterns() -> anyhow::Result<()> {
+    let (path, repo) = create_fresh_repo("include_and_exclude_patterns")?;
+    let page = |name| (name, "title = \"Page\"", "");
+    stage_pages(
+        &repo,
+        &[
+            page("blog/a.md"),
+            page("blog/drafts/b.md"),
+            page("blog/README.md"),
+            page("archive/c.md"),
+            page("about/d.md"),
+        ],
+    )?;
+    git_commands::commit(&repo, "add pages")?;
+
+    let cli = Cli {
+        should_check_only: true,
+        include: vec!["blog/**".to_string(), "archive/**".to_string()],
+        exclude: vec![
+            "archive/**".to_string(),
+            "**/drafts/**".to_string(),
+            "**/README.md".to_string(),
+        ],
+        ..Default::default()
+    };
+    let stats = run_unattended(&path, cli)?;
+    assert_eq!(stats.errors(), 0, "{stats}");
+    assert_eq!(stats.changed(), 1, "{stats}");
+    assert_eq!(stats.skipped_for(SkipReason::Excluded), 3, "{stats}");
+    assert_eq!(stats.skipped_for(SkipReason::NotIncluded), 1, "{stats}");
+    Ok(())
+}
+
+#[rstest]
+#[case(false, 2, 0)]
+#[case(true, 1, 1)]
+fn ignored_and_untracked_files(
+    #[case] tracked_only: bool,
+    #[case] expected_changed: u64,
+    #[case] expected_untracked: u64,
+) -> anyhow::Result<()> {
+    let (path, repo) = create_fresh_repo(&format!("ignored_and_untracked_files_{tracked_only}"))?;
+    std::fs::write(
+        path.join(".gitignore"),
+        "node_modules/\nignored.md\ndebug.log\n",
+    )?;
+    std::fs::create_dir(path.join("node_modules"))?;
+    stage_pages(&repo, &[("tracked.md", "title = \"Page\"", "")])?;
+    git_commands::commit(&repo, "add page")?;
+    for name in ["untracked.md", "ignored.md", "node_modules/readme.md"] {
+        write_page(path.join(name), "title = \"Page\"", "")?;
+    }
+    // Files that are not pages are never counted as ignored or untracked
+    std::fs::write(path.join("debug.log"), "Ignored")?;
+    std::fs::write(path.join("notes.txt"), "Untracked")?;
+
+    let cli = Cli {
+        should_check_only: true,
+        tracked_only,
+        ..Default::default()
+    };
+    let stats = run_unattended(&path, cli)?;
+    assert_eq!(stats.errors(), 0, "{stats}");
+    assert_eq!(stats.changed(), expected_changed, "{stats}");
+    assert_eq!(stats.skipped_for(SkipReason::Ignored), 1, "{stats}");
+    assert_eq!(
+        stats.skipped_for(SkipReason::Untracked),
+        expected_untracked,
+        "{stats}"
+    );
+    assert_eq!(stats.skipped_for(SkipReason::NotMarkdown), 3, "{stats}");
+    Ok(())
+}
+
+/// Runs the executable with `args` (answering the confirmation prompt with `input`)
+fn run_binary(args: &[&str], input: &str) -> anyhow::Result<std::process::Output> {
+    use std::io::Write as _;
+    use std::process::Stdio;
+    let mut child = std::process::Command::new(env!("CARGO_BIN_EXE_zola_chrono"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    child
+        .stdin
+        .take()
+        .context("Failed to open stdin")?
+        .write_all(input.as_bytes())?;
+    Ok(child.wait_with_output()?)
+}
+
+/// Runs the executable with `args` (answering the confirmation prompt with `input`) and returns its exit code
+fn exit_code_of(args: &[&str], input: &str) -> anyhow::Result<i32> {
+    run_binary(args, input)?
+        .status
+        .code()
+        .context("Terminated by a signal")
+}
+
+#[test]
+fn exit_codes() -> anyhow::Result<()> {
+    let (path, repo) = create_fresh_repo("exit_codes")?;
+    commit_pages(
+        &repo,
+        &[("page.md", "date = 2020-01-01", "")],
+        "add page",
+        "2021-01-01",
+    )?;
+    let root = path.to_string_lossy().to_string();
+    let today = ["--today", "2022-03-04"];
+
+    let check = [&today[..], &["-u", "-c", &root]].concat();
+    assert_eq!(exit_code_of(&check, "")?, 2, "would change");
+    let prompt = [&today[..], &[&root]].concat();
+    assert_eq!(exit_code_of(&prompt, "no\n")?, 6, "aborted");
+
+    std::fs::write(path.join("dirty.md"), "Not committed")?;
+    let unattended = [&today[..], &["-u", &root]].concat();
+    assert_eq!(exit_code_of(&unattended, "")?, 3, "unclean version control");
+
+    std::fs::write(path.join("dirty.md"), "No front matter")?;
+    git_commands::add_all(&repo, &["*"])?;
+    git_commands::commit_on(&repo, "add page without front matter", "2021-01-01")?;
+    assert_eq!(exit_code_of(&unattended, "")?, 4, "page errors");
+    let page = path.join("dirty.md").to_string_lossy().to_string();
+    let explain = [&today[..], &["explain", &page]].concat();
+    assert_eq!(exit_code_of(&explain, "")?, 4, "page error in explain");
+    Ok(())
+}
+
+#[test]
+fn exit_code_without_git() -> anyhow::Result<()> {
+    let (path, repo) = create_fresh_repo("exit_code_without_git")?;
+    commit_pages(
+        &repo,
+        &[("page.md", "date = 2020-01-01", "")],
+        "add page",
+        "2021-01-01",
+    )?;
+    // Outside of the repository so that it stays clean
+    let empty_bin = path.with_file_name("exit_code_without_git_bin");
+    std::fs::create_dir_all(&empty_bin)?;
+
+    let output = std::process::Command::new(env!("CARGO_BIN_EXE_zola_chrono"))
+        .args(["-u", "-c", &path.to_string_lossy()])
+        .env("PATH", &empty_bin)
+        .output()?;
+    assert_eq!(
+        output.status.code(),
+        Some(5),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    Ok(())
+}
+
+#[rstest]
+#[case("text", true)]
+#[case("json", false)]
+fn confirmation_prompt_output(
+    #[case] format: &str,
+    #[case] is_prompt_on_stdout: bool,
+) -> anyhow::Result<()> {
+    let (path, repo) = create_fresh_repo(&format!("confirmation_prompt_output_{format}"))?;
+    commit_pages(
+        &repo,
+        &[("page.md", "date = 2020-01-01", "")],
+        "add page",
+        "2021-01-01",
+    )?;
+
+    let output = run_binary(&["--format", format, &path.to_string_lossy()], "no\n")?;
+    assert_eq!(output.status.code(), Some(6));
+    let stdout = String::from_utf8(output.stdout)?;
+    let stderr = String::from_utf8(output.stderr)?;
+    let (prompt_on, other) = if is_prompt_on_stdout {
+        (&stdout, &stderr)
+    } else {
+        (&stderr, &stdout)
+    };
+    assert!(prompt_on.contains("Are you sure"), "{prompt_on}");
+    assert!(
+        prompt_on.contains("Aborted at users request"),
+        "{prompt_on}"
+    );
+    assert!(other.is_empty(), "{other}");
+    Ok(())
+}